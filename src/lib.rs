@@ -1,26 +1,62 @@
 use std::fs::{File};
-use std::io::{stdout, Write, Seek, ErrorKind};
+use std::io::{stdout, Write, ErrorKind};
 use crossterm::{
     execute,
     terminal::{Clear, ClearType, DisableLineWrap, EnableLineWrap},
-    cursor::MoveTo,
-    event::{Event, read, KeyCode, KeyEventKind, KeyModifiers},
-    style::{Color, ResetColor, SetBackgroundColor, SetForegroundColor},
+    cursor::{MoveTo, SetCursorStyle},
+    event::{DisableMouseCapture, EnableMouseCapture},
+    style::ResetColor,
 };
 
 mod t_file;
-use t_file::TFile;
 
 mod t_event;
-use t_event::{InsertEvent, DeleteEvent, Direction, MoveEvent};
+
+mod t_editor;
+use t_editor::TEditor;
 
 mod t_file_explorer;
 use t_file_explorer::TFileExplorer;
 
+mod t_large_file;
+
+mod t_screen;
+use t_screen::screen;
+
+mod t_window;
+
+mod t_options;
+use t_options::{TNumbers, TOptions};
+
+mod t_command;
+
+mod t_mode;
+
+mod t_vim;
+
+mod t_keys;
+
+mod t_dirs;
+
+mod t_marks;
+
+mod t_keymap;
+use t_keymap::{TContext, TKeymap};
+
+mod t_config;
+
+mod t_theme;
+use t_theme::{TColors, TTheme};
 
+mod t_syntax;
+
+mod t_editorconfig;
+
+
+#[derive(Default)]
 pub struct Config
 {
-    pub file_path: String,
+    pub file_paths: Vec<String>,
     pub file_explorer: bool,
     pub create_file: bool,
     pub delete_file: bool,
@@ -30,6 +66,11 @@ pub struct Config
     pub new_file_name: String,
     pub help: bool,
     pub keybinds: bool,
+    pub view: bool,
+    pub pipe: bool,
+    pub tabs: Option<bool>,
+    pub numbers: Option<TNumbers>,
+    pub modal: Option<bool>,
 }
 
 
@@ -38,21 +79,6 @@ const MODIFIERS: [&str; 10] = ["-c", "--create", "-d", "--delete", "-r", "--rena
 
 impl Config
 {
-    pub fn default() -> Config
-    {
-        Config {file_path: String::new(),
-                file_explorer: false,
-                create_file: false,
-                delete_file: false,
-                rename_file: false,
-                dark: false,
-                light: false,
-                new_file_name: String::new(),
-                help: false,
-                keybinds: false,
-            }
-    }
-
     pub fn build(args: &[String]) -> Result<Config, &'static str>
     {
         if args.len() <= 1
@@ -60,29 +86,39 @@ impl Config
             return Err("Not enough arguments");
         }
 
+        let mut c = Config::default();
+        let args = c.read_flags(args);
+
+        if args.len() == 1
+        {
+            if !c.pipe
+            {
+                return Err("Not enough arguments");
+            }
+
+            c.file_paths.push(String::from("-"));
+            return Ok(c);
+        }
+
         if args.len() == 2
         {
             if args[1] == "--files" || args[1] == "-f"
             {
-                let mut c = Config::default();
                 c.file_explorer = true;
                 return Ok(c);
             }
             if args[1] == "--help" || args[1] == "-h"
             {
-                let mut c = Config::default();
                 c.help = true;
                 return Ok(c);
             }
             if args[1] == "--keybinds" || args[1] == "-k"
             {
-                let mut c = Config::default();
                 c.keybinds = true;
                 return Ok(c);
             }
 
-            let mut c = Config::default();
-            c.file_path = args[1].clone();
+            c.file_paths.push(args[1].clone());
             return Ok(c);
         }
 
@@ -90,61 +126,91 @@ impl Config
         {
             if !MODIFIERS.contains(&args[2].as_str())
             {
-                return Err("Invalid modifier");
+                if args[1..].iter().any(|a| a.starts_with('-') && a != "-")
+                {
+                    return Err("Invalid modifier");
+                }
+
+                c.file_paths = args[1..].to_vec();
+                return Ok(c);
             }
 
             let file_path = args[1].clone();
 
             if MODIFIERS[0..2].contains(&args[2].as_str())
             {
-                let mut c = Config::default();
-                c.file_path = file_path;
+                c.file_paths.push(file_path);
                 c.create_file = true;
                 return Ok(c);
             }
             if MODIFIERS[2..4].contains(&args[2].as_str())
             {
-                let mut c = Config::default();
-                c.file_path = file_path;
+                c.file_paths.push(file_path);
                 c.delete_file = true;
                 return Ok(c);
             }
             if MODIFIERS[4..6].contains(&args[2].as_str())
             {
-                let mut c = Config::default();
-                c.file_path = file_path;
+                c.file_paths.push(file_path);
                 c.rename_file = true;
                 return Ok(c);
             }
             if MODIFIERS[6..8].contains(&args[2].as_str())
             {
-                let mut c = Config::default();
-                c.file_path = file_path;
+                c.file_paths.push(file_path);
                 c.dark = true;
                 return Ok(c);
             }
 
-            let mut c = Config::default();
-            c.file_path = file_path;
+            c.file_paths.push(file_path);
             c.light = true;
             return Ok(c);
         }
 
-        if args.len() == 4
+        if args.len() == 4 && MODIFIERS[4..6].contains(&args[2].as_str())
         {
-            if !MODIFIERS[4..6].contains(&args[2].as_str())
-            {
-                return Err("Too many arguments");
-            }
-
-            let mut c = Config::default();
-            c.file_path = args[1].clone();
+            c.file_paths.push(args[1].clone());
             c.new_file_name = args[3].clone();
             c.create_file = true;
             return Ok(c);
         }
 
-        Err("Too many arguments")
+        if args[1..].iter().any(|a| a.starts_with('-') && a != "-")
+        {
+            return Err("Too many arguments");
+        }
+
+        c.file_paths = args[1..].to_vec();
+        Ok(c)
+    }
+
+    // flags that only add to a normal run, so they may appear anywhere
+    fn read_flags(&mut self, args: &[String]) -> Vec<String>
+    {
+        let mut rest = Vec::new();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next()
+        {
+            match arg.as_str()
+            {
+                "-v" | "--view" => { self.view = true; },
+                "-p" | "--pipe" => { self.pipe = true; },
+                "-t" | "--tabs" => { self.tabs = Some(true); },
+                "--no-tabs" => { self.tabs = Some(false); },
+                "-m" | "--modal" => { self.modal = Some(true); },
+                "--no-modal" => { self.modal = Some(false); },
+                "-n" | "--numbers" => {
+                    self.numbers = Some(TNumbers::Absolute);
+                    if let Some(mode) = args.next_if(|a| TNumbers::parse(a).is_some())
+                    {
+                        self.numbers = TNumbers::parse(mode);
+                    }
+                },
+                _ => { rest.push(arg.clone()); },
+            }
+        }
+
+        rest
     }
 }
 
@@ -155,14 +221,24 @@ pub fn run(config: Config) -> Result<(), &'static str>
     {
         print!(r#"Command line text editor like vim. But tim.
 
-Usage: tim <FILE_PATH> [OPTIONS]
+Usage: tim <FILE_PATH>... [OPTIONS]
+       tim - [OPTIONS]     Edits text read from stdin
 
 Options:
     -c, --create        Creates but doesn't open file
     -d, --delete        Deletes file
     -r, --rename [NAME] Renames file to [NAME] or user inputted
-    -b, --dark          White on black
-    -l, --light         Black on white
+    -b, --dark          Uses the dark theme, white on black
+    -l, --light         Uses the light theme, black on white
+    -v, --view          Opens file read-only
+    -p, --pipe          Writes the buffer to stdout on exit
+    -t, --tabs          Shows a tab line with the open files
+        --no-tabs       Hides the tab line
+    -n, --numbers [MODE] Shows line numbers, MODE is absolute, relative, hybrid or off
+    -m, --modal         Vim-style normal, insert and visual modes
+        --no-modal      Plain editing without modes
+
+    These override the settings in config.toml.
 
 Usage: tim [OPTIONS]
 
@@ -176,18 +252,60 @@ Options:
     }
     else if config.keybinds
     {
-        print!(r#"Text Editor:
-    Esc, End, Delete, Ctrl-S => Exit
-    Arrow Keys => Move Cursor
-    Ctrl-Z => Undo
+        let (keymap, error) = TKeymap::load();
+        if let Some(error) = error
+        {
+            eprintln!("{error}");
+        }
 
-File Explorer:
-    Esc, End, Delete, Ctrl-S => Exit
-    Arrow Keys => Move Cursor
-    Enter, Space => Select
-    Backspace => Parent Directory
+        print!("Text Editor:\n{}{}    Click Tab => Select Tab\n\nRead-only View:\n{}\n",
+            keymap.describe(TContext::Global),
+            keymap.describe(TContext::Edit),
+            keymap.describe(TContext::View),
+        );
+        let modal: String = keymap.describe_modal().lines().map(|line| format!("    {line}\n")).collect();
+        print!("Modal Editing (--modal):\n    Every Mode:\n{modal}");
+        print!(r#"    Normal Mode:
+        H/J/K/L, Arrow Keys => Move Cursor
+        W, B, E => Next Word, Previous Word, End Of Word
+        0, ^, $ => Start, First Non-Blank, End Of Line
+        GG, Shift-G => First Line, Last Line Or Line [COUNT]
+        F/T[CHAR], Shift-F/T[CHAR] => Find, Till Character Forward, Backward
+        % => Matching Bracket
+        D, C, Y, >, < [MOTION] => Delete, Change, Yank, Indent, Dedent
+        DD, CC, YY, >>, << => Operate On Whole Lines
+        IW, AW, I(, A(, I", A", IP, AP => Text Objects After An Operator
+        [COUNT] => Repeats A Motion Or Operator, Like 3DW
+        . => Repeat Last Change
+        Q[REGISTER], Q => Record A Macro, Stop Recording
+        @[REGISTER], @@ => Replay A Macro, Replay The Last Macro
+        M[NAME] => Set A Mark
+        '[NAME], `[NAME] => Jump To A Mark's Line, Exact Position
+        I, A => Insert Before, After The Cursor
+        Shift-I, Shift-A => Insert At The Start, End Of The Line
+        O, Shift-O => Open A Line Below, Above
+        X => Delete Character
+        P, Shift-P => Put After, Before The Cursor
+        U => Undo
+        V => Visual Mode
+        : => Command Line
+        / => Search
+        N, Shift-N => Next Match, Previous Match
+    Insert Mode:
+        Esc => Normal Mode
+        Other Keys => Like The Text Editor
+    Visual Mode:
+        Motions, Arrow Keys => Extend Selection
+        O => Other End Of Selection
+        Y => Yank Selection
+        D, X => Delete Selection
+        C => Change Selection
+        >, < => Indent, Dedent Selection
+        Esc, V => Normal Mode
 
 "#);
+        print!("Command Line:\n{}    Backspace On An Empty Line => Cancel\n\n", keymap.describe(TContext::Command));
+        print!("File Explorer:\n{}\n", keymap.describe(TContext::Explorer));
         Ok(())
     }
     else if config.file_explorer
@@ -196,35 +314,44 @@ File Explorer:
     }
     else if config.create_file
     {
-        create_file(config.file_path.as_str())
+        config.file_paths.iter().try_for_each(|path| create_file(path.as_str()))
     }
     else if config.delete_file
     {
-        delete_file(config.file_path.as_str())
+        config.file_paths.iter().try_for_each(|path| delete_file(path.as_str()))
     }
     else if config.rename_file
     {
-        rename_file(config.file_path.as_str(), config.new_file_name)
+        rename_file(config.file_paths[0].as_str(), config.new_file_name)
     }
     else
     {
+        let mut options = TOptions::default();
+        let error = t_config::load(&mut options);
+
+        options.view |= config.view;
+        if let Some(tabs) = config.tabs
+        {
+            options.tab_bar = tabs;
+        }
+        if let Some(modal) = config.modal
+        {
+            options.modal = modal;
+        }
+        if let Some(numbers) = config.numbers
+        {
+            options.numbers = numbers;
+        }
         if config.dark
         {
-            execute!(
-                stdout(),
-                SetForegroundColor(Color::White),
-                SetBackgroundColor(Color::Black),
-            ).unwrap();
+            options.theme = String::from("dark");
         }
         else if config.light
         {
-            execute!(
-                stdout(),
-                SetForegroundColor(Color::Black),
-                SetBackgroundColor(Color::White),
-            ).unwrap();
+            options.theme = String::from("light");
         }
-        text_editor(config.file_path.as_str())
+
+        text_editor(&config.file_paths, options, config.pipe, error)
     }
 }
 
@@ -294,57 +421,21 @@ fn rename_file(path: &str, mut name: String) -> Result<(), &'static str>
 
 fn file_explorer() -> Result<(), &'static str>
 {
-    let mut t_file_explorer = TFileExplorer::new();
-    let selected_path: &str;
-
     crossterm::terminal::enable_raw_mode().unwrap();
 
     execute!(
-        stdout(),
+        screen(),
         DisableLineWrap,
     ).unwrap();
 
-    t_file_explorer.clear_screen().unwrap();
+    let mut options = TOptions::default();
+    let error = t_config::load(&mut options);
 
-    loop
-    {
-        match read().unwrap()
-        {
-            Event::Key(event) => {
-                if event.kind == KeyEventKind::Press
-                {
-                    match event.code
-                    {
-                        KeyCode::Esc | KeyCode::End | KeyCode::Delete => { selected_path = ""; break; },
-                        KeyCode::Char('s') =>
-                            {
-                                if event.modifiers == KeyModifiers::CONTROL
-                                {
-                                    selected_path = "";
-                                    break;
-                                }
-                            },
-
-                        KeyCode::Up => { t_file_explorer.move_up().unwrap(); },
-                        KeyCode::Down => { t_file_explorer.move_down().unwrap(); },
-                        KeyCode::Enter | KeyCode::Char(' ') => {
-                            match t_file_explorer.select()
-                            {
-                                None => { t_file_explorer.make_paths(); },
-                                Some(path) => { selected_path = path; break; },
-                            };
-                        },
-                        KeyCode::Backspace => { t_file_explorer.back().unwrap(); },
-                        _ => {},
-                    }
-                }
-            },
-            _ => {},
-        }
-    }
+    let (theme, _) = load_theme(&options);
+    let selected_path = TFileExplorer::new(&options.explorer, theme).pick(&TKeymap::load().0);
 
     execute!(
-        stdout(),
+        screen(),
         EnableLineWrap,
         Clear(ClearType::All),
         Clear(ClearType::Purge),
@@ -353,86 +444,46 @@ fn file_explorer() -> Result<(), &'static str>
 
     crossterm::terminal::disable_raw_mode().unwrap();
 
-    if selected_path.is_empty()
-    {
-        Ok(())
-    }
-    else
+    match selected_path
     {
-        text_editor(selected_path)
+        None => Ok(()),
+        Some(path) => text_editor(&[path], options, false, error),
     }
 }
 
 
-fn text_editor(path: &str) -> Result<(), &'static str>
+fn load_theme(options: &TOptions) -> (TTheme, Option<String>)
 {
-    let mut t_file;
-    match open_file(path)
+    let colors = options.colors.unwrap_or_else(TColors::detect);
+    match TTheme::load(options.theme.as_str(), colors)
     {
-        Ok(f) => t_file = f,
-        Err(err) => return Err(err),
+        Ok(theme) => (theme, None),
+        Err(err) => (TTheme::built_in(colors), Some(err)),
     }
+}
 
-    crossterm::terminal::enable_raw_mode().unwrap();
 
-    t_file.clear_screen().unwrap();
+fn text_editor(paths: &[String], options: TOptions, pipe: bool, error: Option<String>) -> Result<(), &'static str>
+{
+    let (theme, theme_error) = load_theme(&options);
+    let (keymap, keymap_error) = TKeymap::load();
+    let syntax_error = t_syntax::load_languages();
+    let mut t_editor = TEditor::build(paths, options, keymap, theme)?;
+    t_editor.message = [error, theme_error, keymap_error, syntax_error].into_iter().flatten().collect::<Vec<String>>().join("  ");
 
-    loop
+    crossterm::terminal::enable_raw_mode().unwrap();
+
+    if t_editor.options.tab_bar
     {
-        match read().unwrap()
-        {
-            Event::Key(event) => {
-                if event.kind == KeyEventKind::Press
-                {
-                    match event.code
-                    {
-                        KeyCode::Esc | KeyCode::End | KeyCode::Delete => break,
-                        KeyCode::Char('s') =>
-                            {
-                                if event.modifiers == KeyModifiers::CONTROL
-                                {
-                                    break;
-                                }
-                                t_file.add_event(InsertEvent(event.code.to_string()));
-                            },
-
-                        KeyCode::Up => { t_file.add_event(MoveEvent(Direction::Up, 0)); },
-                        KeyCode::Down => { t_file.add_event(MoveEvent(Direction::Down, 0)); },
-                        KeyCode::Left => { t_file.add_event(MoveEvent(Direction::Left, 0)); },
-                        KeyCode::Right => { t_file.add_event(MoveEvent(Direction::Right, 0)); },
-
-                        KeyCode::Enter => { t_file.add_event(InsertEvent(String::from("\n"))); },
-                        KeyCode::Backspace => { t_file.add_event(DeleteEvent(1, String::new())); },
-
-                        KeyCode::Char('z') =>
-                            {
-                                if event.modifiers == KeyModifiers::CONTROL
-                                {
-                                    t_file.undo();
-                                }
-                                else
-                                {
-                                    t_file.add_event(InsertEvent(event.code.to_string()));
-                                }
-                            },
-
-                        KeyCode::Tab => { t_file.add_event(InsertEvent(String::from("    "))); },
-                        KeyCode::Char(' ') => { t_file.add_event(InsertEvent(String::from(" "))); },
-                        _ => {
-                            if event.code.to_string().len() == 1
-                            {
-                                t_file.add_event(InsertEvent(event.code.to_string()));
-                            }
-                        },
-                    }
-                }
-            },
-            _ => {},
-        }
+        execute!(screen(), EnableMouseCapture).unwrap();
     }
 
+    t_editor.run();
+
     execute!(
-        stdout(),
+        screen(),
+        DisableMouseCapture,
+        SetCursorStyle::DefaultUserShape,
         ResetColor,
         Clear(ClearType::All),
         Clear(ClearType::Purge),
@@ -441,27 +492,10 @@ fn text_editor(path: &str) -> Result<(), &'static str>
 
     crossterm::terminal::disable_raw_mode().unwrap();
 
-    t_file.file.set_len(0).unwrap();
-    t_file.file.rewind().unwrap();
-    t_file.file.write(t_file.content.as_ref()).unwrap();
-
-    Ok(())
-}
-
-
-fn open_file(file_path: &str) -> Result<TFile, &'static str>
-{
-    match File::options().write(true).read(true).create(true).open(file_path)
+    if pipe && stdout().write_all(&t_editor.buffers[0].to_bytes()).is_err()
     {
-        Ok(f) => match TFile::build(f) {
-            Ok(t_file) => Ok(t_file),
-            Err(err) => Err(err),
-        },
-        Err(err) => {
-            match err.kind()
-            {
-                _ => Err("File cannot be opened"),
-            }
-        },
+        return Err("Cannot write to stdout.");
     }
+
+    Ok(())
 }
@@ -0,0 +1,530 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use crossterm::{
+    queue,
+    cursor::MoveTo,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{ContentStyle, PrintStyledContent},
+};
+
+use crate::t_dirs::macros_dir;
+use crate::t_options::TOptions;
+use crate::t_theme::TTheme;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TCommandName
+{
+    Write,
+    Quit,
+    WriteQuit,
+    Exit,
+    Edit,
+    Substitute,
+    Set,
+    Goto,
+    BufferNext,
+    BufferPrevious,
+    Split,
+    VerticalSplit,
+    Close,
+    NoHighlight,
+    MacroSave,
+    MacroLoad,
+    Mark,
+    Marks,
+    DeleteMarks,
+    GotoMark,
+    Indent,
+}
+
+
+pub struct TCommand
+{
+    pub name: &'static str,
+    pub short: &'static str,
+    pub command: TCommandName,
+}
+
+
+pub const COMMANDS: [TCommand; 19] = [
+    TCommand { name: "write", short: "w", command: TCommandName::Write },
+    TCommand { name: "quit", short: "q", command: TCommandName::Quit },
+    TCommand { name: "wq", short: "wq", command: TCommandName::WriteQuit },
+    TCommand { name: "xit", short: "x", command: TCommandName::Exit },
+    TCommand { name: "edit", short: "e", command: TCommandName::Edit },
+    TCommand { name: "substitute", short: "s", command: TCommandName::Substitute },
+    TCommand { name: "set", short: "se", command: TCommandName::Set },
+    TCommand { name: "bnext", short: "bn", command: TCommandName::BufferNext },
+    TCommand { name: "bprevious", short: "bp", command: TCommandName::BufferPrevious },
+    TCommand { name: "split", short: "sp", command: TCommandName::Split },
+    TCommand { name: "vsplit", short: "vs", command: TCommandName::VerticalSplit },
+    TCommand { name: "close", short: "clo", command: TCommandName::Close },
+    TCommand { name: "nohlsearch", short: "noh", command: TCommandName::NoHighlight },
+    TCommand { name: "msave", short: "ms", command: TCommandName::MacroSave },
+    TCommand { name: "mload", short: "ml", command: TCommandName::MacroLoad },
+    TCommand { name: "mark", short: "ma", command: TCommandName::Mark },
+    TCommand { name: "marks", short: "marks", command: TCommandName::Marks },
+    TCommand { name: "delmarks", short: "delm", command: TCommandName::DeleteMarks },
+    TCommand { name: "indent", short: "ind", command: TCommandName::Indent },
+];
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TRange
+{
+    Line,
+    All,
+}
+
+
+pub struct TCommandCall
+{
+    pub command: TCommandName,
+    pub range: TRange,
+    pub bang: bool,
+    pub args: String,
+}
+
+
+pub fn parse(input: &str) -> Result<TCommandCall, &'static str>
+{
+    let input = input.trim();
+
+    if input == "$" || (!input.is_empty() && input.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Ok(TCommandCall { command: TCommandName::Goto, range: TRange::Line, bang: false, args: input.to_string() });
+    }
+
+    if let Some(mark) = input.strip_prefix('\'')
+    {
+        return Ok(TCommandCall { command: TCommandName::GotoMark, range: TRange::Line, bang: false, args: mark.to_string() });
+    }
+
+    let (range, input) = match input.strip_prefix('%')
+    {
+        Some(rest) => (TRange::All, rest),
+        None => (TRange::Line, input),
+    };
+
+    let end = input.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(input.len());
+    let (name, rest) = input.split_at(end);
+    let (bang, rest) = match rest.strip_prefix('!')
+    {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+
+    let command = find(name).ok_or("Not an editor command.")?;
+
+    if range == TRange::All && command != TCommandName::Substitute
+    {
+        return Err("No range allowed.");
+    }
+
+    Ok(TCommandCall { command, range, bang, args: rest.trim().to_string() })
+}
+
+
+fn find(name: &str) -> Option<TCommandName>
+{
+    if name.is_empty()
+    {
+        return None;
+    }
+
+    COMMANDS.iter()
+        .find(|c| c.name.starts_with(name) && name.len() >= c.short.len())
+        .map(|c| c.command)
+}
+
+
+pub fn parse_substitute(args: &str) -> Result<(String, String, bool), &'static str>
+{
+    let mut chars = args.chars();
+    let delimiter = match chars.next()
+    {
+        Some(c) if !c.is_alphanumeric() && c != '\\' && !c.is_whitespace() => c,
+        _ => return Err("Usage: s/pattern/replacement/[g]"),
+    };
+
+    let mut parts = vec![String::new()];
+    while let Some(c) = chars.next()
+    {
+        match c
+        {
+            '\\' => match chars.next()
+            {
+                Some(n) if n == delimiter || n == '\\' => parts.last_mut().unwrap().push(n),
+                Some('n') => parts.last_mut().unwrap().push('\n'),
+                Some('t') => parts.last_mut().unwrap().push('\t'),
+                Some(n) => { parts.last_mut().unwrap().push('\\'); parts.last_mut().unwrap().push(n); },
+                None => parts.last_mut().unwrap().push('\\'),
+            },
+            c if c == delimiter && parts.len() < 3 => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    let pattern = parts[0].clone();
+    let replacement = parts.get(1).cloned().unwrap_or_default();
+    let flags = parts.get(2).cloned().unwrap_or_default();
+
+    if pattern.is_empty()
+    {
+        return Err("Empty pattern.");
+    }
+    if flags.chars().any(|c| c != 'g')
+    {
+        return Err("Invalid substitute flag.");
+    }
+
+    Ok((pattern, replacement, flags.contains('g')))
+}
+
+
+pub struct TCommandLine
+{
+    pub prefix: Option<char>,
+    pub input: String,
+    cursor: usize,
+    history: Vec<(char, String)>,
+    history_index: usize,
+    draft: String,
+    completions: Vec<String>,
+    completion: usize,
+    completion_start: usize,
+}
+
+
+impl TCommandLine
+{
+    pub fn new() -> TCommandLine
+    {
+        TCommandLine {
+            prefix: None,
+            input: String::new(),
+            cursor: 0,
+            history: Vec::new(),
+            history_index: 0,
+            draft: String::new(),
+            completions: Vec::new(),
+            completion: 0,
+            completion_start: 0,
+        }
+    }
+
+    pub fn open(&mut self, prefix: char)
+    {
+        self.prefix = Some(prefix);
+        self.input.clear();
+        self.cursor = 0;
+        self.history_index = self.entries().len();
+        self.completions.clear();
+    }
+
+    pub fn submit(&mut self) -> Option<String>
+    {
+        let prefix = self.prefix.take()?;
+        let input = self.input.clone();
+        if !input.trim().is_empty() && self.entries_for(prefix).last() != Some(&input.as_str())
+        {
+            self.history.push((prefix, input.clone()));
+        }
+
+        Some(input)
+    }
+
+    pub fn cancel(&mut self)
+    {
+        self.prefix = None;
+    }
+
+    pub fn handle_key(&mut self, event: KeyEvent)
+    {
+        self.completions.clear();
+
+        match event.code
+        {
+            KeyCode::Backspace if self.input.is_empty() => { self.prefix = None; },
+            KeyCode::Backspace => {
+                if let Some(c) = self.input[..self.cursor].chars().next_back()
+                {
+                    self.cursor -= c.len_utf8();
+                    self.input.remove(self.cursor);
+                }
+            },
+            KeyCode::Delete if self.cursor < self.input.len() => { self.input.remove(self.cursor); },
+            KeyCode::Left => {
+                if let Some(c) = self.input[..self.cursor].chars().next_back()
+                {
+                    self.cursor -= c.len_utf8();
+                }
+            },
+            KeyCode::Right => {
+                if let Some(c) = self.input[self.cursor..].chars().next()
+                {
+                    self.cursor += c.len_utf8();
+                }
+            },
+            KeyCode::Home => { self.cursor = 0; },
+            KeyCode::End => { self.cursor = self.input.len(); },
+            KeyCode::Char('u') if event.modifiers == KeyModifiers::CONTROL => {
+                self.input.drain(..self.cursor);
+                self.cursor = 0;
+            },
+            KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+
+    pub fn draw(&self, screen: &mut impl Write, cols: u16, rows: u16, style: ContentStyle) -> std::io::Result<()>
+    {
+        let prefix = self.prefix.unwrap_or(':');
+        let column = self.input[..self.cursor].chars().count() + 1;
+        let skip = (column + 1).saturating_sub(cols as usize);
+        let line: String = format!("{prefix}{}", self.input).chars().skip(skip).take(cols as usize).collect();
+        let width = cols as usize;
+
+        queue!(
+            screen,
+            MoveTo(0, rows - 1),
+            PrintStyledContent(style.apply(format!("{line:<width$}"))),
+            MoveTo((column - skip) as u16, rows - 1),
+        )
+    }
+
+    fn entries(&self) -> Vec<&str>
+    {
+        self.entries_for(self.prefix.unwrap_or(':'))
+    }
+
+    fn entries_for(&self, prefix: char) -> Vec<&str>
+    {
+        self.history.iter().filter(|(p, _)| *p == prefix).map(|(_, h)| h.as_str()).collect()
+    }
+
+    pub fn step_history(&mut self, step: isize)
+    {
+        self.completions.clear();
+        let entries: Vec<String> = self.entries().into_iter().map(String::from).collect();
+        let index = self.history_index as isize + step;
+        if index < 0 || index > entries.len() as isize
+        {
+            return;
+        }
+
+        if self.history_index == entries.len()
+        {
+            self.draft = self.input.clone();
+        }
+
+        self.history_index = index as usize;
+        self.input = entries.get(self.history_index).cloned().unwrap_or(self.draft.clone());
+        self.cursor = self.input.len();
+    }
+
+    pub fn complete(&mut self, step: isize)
+    {
+        if self.prefix != Some(':')
+        {
+            return;
+        }
+
+        if self.completions.is_empty()
+        {
+            let (start, completions) = completions(&self.input[..self.cursor]);
+            if completions.is_empty()
+            {
+                return;
+            }
+            self.completion_start = start;
+            self.completions = completions;
+            self.completion = if step > 0 { 0 } else { self.completions.len() - 1 };
+        }
+        else
+        {
+            let count = self.completions.len() as isize;
+            self.completion = ((self.completion as isize + step).rem_euclid(count)) as usize;
+        }
+
+        let completion = &self.completions[self.completion];
+        self.input.replace_range(self.completion_start..self.cursor, completion);
+        self.cursor = self.completion_start + completion.len();
+    }
+}
+
+
+fn completions(input: &str) -> (usize, Vec<String>)
+{
+    let word_start = input.trim_start().len();
+    let offset = input.len() - word_start;
+    let trimmed = input.trim_start();
+
+    let name_end = trimmed.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(trimmed.len());
+    if name_end == trimmed.len()
+    {
+        let mut names: Vec<String> = COMMANDS.iter()
+            .filter(|c| c.name.starts_with(trimmed))
+            .map(|c| c.name.to_string())
+            .collect();
+        names.sort();
+        return (offset, names);
+    }
+
+    let start = input.rfind(' ').map_or(input.len(), |i| i + 1);
+    let word = &input[start..];
+
+    match find(&trimmed[..name_end])
+    {
+        Some(TCommandName::Write) | Some(TCommandName::Edit) | Some(TCommandName::WriteQuit)
+            | Some(TCommandName::Split) | Some(TCommandName::VerticalSplit) => (start, complete_path(word)),
+        Some(TCommandName::Set) => {
+            if let Some(theme) = word.strip_prefix("theme=")
+            {
+                let names = TTheme::names().into_iter().filter(|n| n.starts_with(theme)).collect();
+                return (input.len() - theme.len(), names);
+            }
+            let word = word.strip_prefix("no").unwrap_or(word);
+            let names = TOptions::NAMES.iter()
+                .filter(|n| n.starts_with(word))
+                .map(|n| n.to_string())
+                .collect();
+            (input.len() - word.len(), names)
+        },
+        Some(TCommandName::MacroLoad) if !trimmed[name_end..].trim().contains(' ') => (start, complete_macro(word)),
+        _ => (start, Vec::new()),
+    }
+}
+
+
+fn complete_macro(word: &str) -> Vec<String>
+{
+    let entries = match macros_dir().map(fs::read_dir)
+    {
+        Some(Ok(entries)) => entries,
+        _ => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(word))
+        .collect();
+    names.sort();
+
+    names
+}
+
+
+fn complete_path(word: &str) -> Vec<String>
+{
+    let (directory, name) = match word.rfind('/')
+    {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None => ("", word),
+    };
+
+    let entries = match fs::read_dir(if directory.is_empty() { "." } else { directory })
+    {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.'))
+            {
+                return None;
+            }
+            let slash = if Path::new(directory).join(&file_name).is_dir() { "/" } else { "" };
+            Some(format!("{directory}{file_name}{slash}"))
+        })
+        .collect();
+    paths.sort();
+
+    paths
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parses_line_numbers_and_marks()
+    {
+        let call = parse(" 42 ").unwrap();
+        assert!(call.command == TCommandName::Goto && call.args == "42");
+        assert!(parse("$").unwrap().command == TCommandName::Goto);
+
+        let call = parse("'a").unwrap();
+        assert!(call.command == TCommandName::GotoMark && call.args == "a");
+    }
+
+    #[test]
+    fn parses_names_bang_and_args()
+    {
+        let call = parse("w! out.txt").unwrap();
+        assert!(call.command == TCommandName::Write && call.bang && call.args == "out.txt");
+
+        assert!(parse("q").unwrap().command == TCommandName::Quit);
+        assert!(parse("wq").unwrap().command == TCommandName::WriteQuit);
+        assert!(parse("vs").unwrap().command == TCommandName::VerticalSplit);
+        assert!(parse("marks").unwrap().command == TCommandName::Marks);
+        assert!(parse("ma b").unwrap().command == TCommandName::Mark);
+    }
+
+    #[test]
+    fn rejects_unknown_and_short_names()
+    {
+        assert!(parse("").is_err());
+        assert!(parse("foo").is_err());
+        assert!(parse("v").is_err());
+        assert!(parse("clos").unwrap().command == TCommandName::Close);
+        assert!(parse("cl").is_err());
+    }
+
+    #[test]
+    fn allows_a_range_only_for_substitute()
+    {
+        let call = parse("%s/a/b/g").unwrap();
+        assert!(call.command == TCommandName::Substitute && call.range == TRange::All && call.args == "/a/b/g");
+        assert!(parse("s/a/b/").unwrap().range == TRange::Line);
+        assert!(parse("%w").is_err());
+    }
+
+    #[test]
+    fn parses_substitute_arguments()
+    {
+        assert_eq!(parse_substitute("/a/b/"), Ok((String::from("a"), String::from("b"), false)));
+        assert_eq!(parse_substitute("/a/b/g"), Ok((String::from("a"), String::from("b"), true)));
+        assert_eq!(parse_substitute("/a"), Ok((String::from("a"), String::new(), false)));
+        assert_eq!(parse_substitute("#a/b#c#"), Ok((String::from("a/b"), String::from("c"), false)));
+    }
+
+    #[test]
+    fn parses_substitute_escapes()
+    {
+        assert_eq!(parse_substitute(r"/a\/b/c\\d/"), Ok((String::from("a/b"), String::from(r"c\d"), false)));
+        assert_eq!(parse_substitute(r"/a\nb/\t/"), Ok((String::from("a\nb"), String::from("\t"), false)));
+        assert_eq!(parse_substitute(r"/a\.b/c/"), Ok((String::from(r"a\.b"), String::from("c"), false)));
+    }
+
+    #[test]
+    fn rejects_bad_substitutes()
+    {
+        assert!(parse_substitute("").is_err());
+        assert!(parse_substitute("abc").is_err());
+        assert!(parse_substitute(" a b ").is_err());
+        assert!(parse_substitute("//b/").is_err());
+        assert!(parse_substitute("/a/b/x").is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::fs;
+
+use crate::t_dirs::config_dir;
+use crate::t_options::{TNumbers, TOptions};
+use crate::t_theme::TColors;
+
+
+pub enum TValue
+{
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+
+pub type TSetting = (String, TValue);
+
+
+pub fn load(options: &mut TOptions) -> Option<String>
+{
+    let content = match config_dir().map(|dir| fs::read_to_string(dir.join("config.toml")))
+    {
+        Some(Ok(content)) => content,
+        _ => return None,
+    };
+
+    let mut errors = Vec::new();
+    for (line, entry) in entries(content.as_str())
+    {
+        if let Err(err) = entry.and_then(|(key, value)| apply(options, key.as_str(), value))
+        {
+            errors.push(format!("config.toml line {line}: {err}"));
+        }
+    }
+
+    match errors.len()
+    {
+        0 => None,
+        1 => Some(errors.remove(0)),
+        n => Some(format!("{} (and {} more)", errors[0], n - 1)),
+    }
+}
+
+
+pub fn entries(content: &str) -> Vec<(usize, Result<TSetting, &'static str>)>
+{
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    for (i, line) in content.lines().enumerate()
+    {
+        match parse_line(line)
+        {
+            Ok(None) => {},
+            Ok(Some(TEntry::Table(name))) => { table = name; },
+            Ok(Some(TEntry::Pair(key, value))) if table.is_empty() => entries.push((i + 1, Ok((key, value)))),
+            Ok(Some(TEntry::Pair(key, value))) => entries.push((i + 1, Ok((format!("{table}.{key}"), value)))),
+            Err(err) => entries.push((i + 1, Err(err))),
+        }
+    }
+
+    entries
+}
+
+
+fn apply(options: &mut TOptions, key: &str, value: TValue) -> Result<(), &'static str>
+{
+    match (key, value)
+    {
+        ("theme", TValue::String(theme)) => { options.theme = theme; },
+        ("colors", TValue::String(colors)) => {
+            options.colors = Some(TColors::parse(colors.as_str()).ok_or("colors is truecolor, 256 or 16.")?);
+        },
+        ("tab_width", TValue::Integer(width)) => {
+            options.tab_width = usize::try_from(width).ok().filter(|w| (1..=16).contains(w)).ok_or("tab_width must be between 1 and 16.")?;
+        },
+        ("soft_tabs", TValue::Boolean(soft)) => { options.soft_tabs = soft; },
+        ("detect_indent", TValue::Boolean(detect)) => { options.detect_indent = detect; },
+        ("syntax", TValue::Boolean(syntax)) => { options.syntax = syntax; },
+        ("auto_indent", TValue::Boolean(auto_indent)) => { options.auto_indent = auto_indent; },
+        ("wrap", TValue::Boolean(wrap)) => { options.wrap = wrap; },
+        ("word_wrap", TValue::Boolean(word_wrap)) => { options.word_wrap = word_wrap; },
+        ("wrap_indent", TValue::Boolean(wrap_indent)) => { options.wrap_indent = wrap_indent; },
+        ("wrap_marker", TValue::String(marker)) => { options.wrap_marker = marker; },
+        ("visual_rows", TValue::Boolean(visual_rows)) => { options.visual_rows = visual_rows; },
+        ("line_numbers", TValue::Boolean(numbers)) => {
+            options.numbers = if numbers { TNumbers::Absolute } else { TNumbers::Off };
+        },
+        ("line_numbers", TValue::String(mode)) => {
+            options.numbers = TNumbers::parse(mode.as_str()).ok_or("line_numbers is off, absolute, relative or hybrid.")?;
+        },
+        ("autosave", TValue::Integer(seconds)) => {
+            options.autosave = u64::try_from(seconds).map_err(|_| "autosave must not be negative.")?;
+        },
+        ("undo_limit", TValue::Integer(limit)) => {
+            options.undo_limit = usize::try_from(limit).ok().filter(|l| *l > 0).ok_or("undo_limit must be at least 1.")?;
+        },
+        ("modal", TValue::Boolean(modal)) => { options.modal = modal; },
+        ("tabs", TValue::Boolean(tabs)) => { options.tab_bar = tabs; },
+        ("explorer.directory", TValue::String(directory)) => { options.explorer.directory = directory; },
+        ("explorer.hidden", TValue::Boolean(hidden)) => { options.explorer.hidden = hidden; },
+        ("explorer.dirs_first", TValue::Boolean(dirs_first)) => { options.explorer.dirs_first = dirs_first; },
+
+        ("theme" | "colors" | "wrap_marker" | "explorer.directory", _) => return Err("Expected a string."),
+        ("tab_width" | "autosave" | "undo_limit", _) => return Err("Expected a number."),
+        ("line_numbers", _) => return Err("Expected a string or a boolean."),
+        ("soft_tabs" | "detect_indent" | "syntax" | "auto_indent" | "wrap" | "word_wrap" | "wrap_indent" | "visual_rows" | "modal" | "tabs" | "explorer.hidden" | "explorer.dirs_first", _) => return Err("Expected true or false."),
+        _ => return Err("Unknown setting."),
+    }
+
+    Ok(())
+}
+
+
+enum TEntry
+{
+    Table(String),
+    Pair(String, TValue),
+}
+
+
+fn parse_line(line: &str) -> Result<Option<TEntry>, &'static str>
+{
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#')
+    {
+        return Ok(None);
+    }
+
+    if let Some(rest) = line.strip_prefix('[')
+    {
+        let end = rest.find(']').ok_or("Missing ].")?;
+        if !is_comment(&rest[end + 1..])
+        {
+            return Err("Unexpected text after the table name.");
+        }
+        let name = rest[..end].trim();
+        if !is_key(name)
+        {
+            return Err("Invalid table name.");
+        }
+        return Ok(Some(TEntry::Table(name.to_string())));
+    }
+
+    let (key, rest) = line.split_once('=').ok_or("Expected KEY = VALUE.")?;
+    let key = key.trim();
+    if !is_key(key)
+    {
+        return Err("Invalid key.");
+    }
+
+    let (value, rest) = parse_value(rest.trim_start())?;
+    if !is_comment(rest)
+    {
+        return Err("Unexpected text after the value.");
+    }
+
+    Ok(Some(TEntry::Pair(key.to_string(), value)))
+}
+
+
+fn parse_value(input: &str) -> Result<(TValue, &str), &'static str>
+{
+    if let Some(rest) = input.strip_prefix('\'')
+    {
+        let end = rest.find('\'').ok_or("Unterminated string.")?;
+        return Ok((TValue::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+
+    if let Some(rest) = input.strip_prefix('"')
+    {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next()
+        {
+            match c
+            {
+                '"' => return Ok((TValue::String(value), &rest[i + 1..])),
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, e)| e)
+                    {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        _ => return Err("Invalid escape in string."),
+                    };
+                    value.push(escaped);
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err("Unterminated string.");
+    }
+
+    let end = input.find(|c: char| c.is_whitespace() || c == '#').unwrap_or(input.len());
+    let (word, rest) = input.split_at(end);
+    let value = match word
+    {
+        "" => return Err("Missing value."),
+        "true" => TValue::Boolean(true),
+        "false" => TValue::Boolean(false),
+        _ => TValue::Integer(word.replace('_', "").parse().map_err(|_| "Invalid value.")?),
+    };
+
+    Ok((value, rest))
+}
+
+
+fn is_key(key: &str) -> bool
+{
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+
+fn is_comment(rest: &str) -> bool
+{
+    let rest = rest.trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn value(line: &str) -> Result<TValue, &'static str>
+    {
+        match parse_line(line)?
+        {
+            Some(TEntry::Pair(_, value)) => Ok(value),
+            _ => Err("Not a pair."),
+        }
+    }
+
+    #[test]
+    fn parses_values()
+    {
+        assert!(matches!(value("a = true"), Ok(TValue::Boolean(true))));
+        assert!(matches!(value("a=false"), Ok(TValue::Boolean(false))));
+        assert!(matches!(value("a = -12"), Ok(TValue::Integer(-12))));
+        assert!(matches!(value("a = 1_000"), Ok(TValue::Integer(1000))));
+        assert!(matches!(value("a = 'C:\\path'"), Ok(TValue::String(s)) if s == "C:\\path"));
+        assert!(matches!(value(r#"a = "x\t\"y\"\n""#), Ok(TValue::String(s)) if s == "x\t\"y\"\n"));
+    }
+
+    #[test]
+    fn allows_comments_after_values()
+    {
+        assert!(matches!(value("a = 4 # four"), Ok(TValue::Integer(4))));
+        assert!(matches!(value("a = \"#\" # hash"), Ok(TValue::String(s)) if s == "#"));
+        assert!(matches!(parse_line("  # only a comment"), Ok(None)));
+        assert!(matches!(parse_line(""), Ok(None)));
+    }
+
+    #[test]
+    fn rejects_invalid_lines()
+    {
+        assert!(value("a =").is_err());
+        assert!(value("a = yes").is_err());
+        assert!(value("a = \"open").is_err());
+        assert!(value("a = \"\\q\"").is_err());
+        assert!(value("a = 1 2").is_err());
+        assert!(value("a b = 1").is_err());
+        assert!(parse_line("just words").is_err());
+        assert!(parse_line("[table").is_err());
+        assert!(parse_line("[table] x").is_err());
+    }
+
+    #[test]
+    fn prefixes_keys_with_their_table()
+    {
+        let content = "theme = \"dark\"\n\n[explorer]\nhidden = true\nbad\n";
+        let entries = entries(content);
+
+        assert_eq!(entries.len(), 3);
+        assert!(matches!(&entries[0], (1, Ok((key, TValue::String(s)))) if key == "theme" && s == "dark"));
+        assert!(matches!(&entries[1], (4, Ok((key, TValue::Boolean(true)))) if key == "explorer.hidden"));
+        assert!(matches!(&entries[2], (5, Err(_))));
+    }
+
+    #[test]
+    fn applies_settings()
+    {
+        let mut options = TOptions::default();
+        apply(&mut options, "tab_width", TValue::Integer(8)).unwrap();
+        apply(&mut options, "line_numbers", TValue::String(String::from("relative"))).unwrap();
+        apply(&mut options, "explorer.hidden", TValue::Boolean(true)).unwrap();
+
+        assert_eq!(options.tab_width, 8);
+        assert!(options.numbers == TNumbers::Relative);
+        assert!(options.explorer.hidden);
+
+        assert!(apply(&mut options, "tab_width", TValue::Integer(0)).is_err());
+        assert!(apply(&mut options, "tab_width", TValue::Boolean(true)).is_err());
+        assert!(apply(&mut options, "unknown", TValue::Boolean(true)).is_err());
+    }
+}
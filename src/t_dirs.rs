@@ -0,0 +1,34 @@
+use std::env;
+use std::path::PathBuf;
+
+
+pub fn data_dir() -> Option<PathBuf>
+{
+    let base = match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty())
+    {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local").join("share"),
+    };
+
+    Some(base.join("tim"))
+}
+
+
+pub fn macros_dir() -> Option<PathBuf>
+{
+    Some(data_dir()?.join("macros"))
+}
+
+
+pub fn config_dir() -> Option<PathBuf>
+{
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty())
+    {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("tim"))
+}
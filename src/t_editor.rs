@@ -0,0 +1,1829 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crossterm::{
+    execute, queue,
+    cursor::MoveTo,
+    event::{DisableMouseCapture, EnableMouseCapture, Event, poll, read, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
+    style::{Print, PrintStyledContent, SetStyle},
+    terminal::{size, Clear, ClearType, DisableLineWrap, EnableLineWrap},
+};
+
+use crate::t_dirs::macros_dir;
+use crate::t_keys::{format_keys, parse_keys};
+use crate::t_keymap::{is_plain, TContext, TKeyAction, TKeymap, TLookup};
+use crate::t_command::{parse, parse_substitute, TCommandCall, TCommandLine, TCommandName, TRange};
+use crate::t_event::{InsertEvent, DeleteEvent, Direction, MoveEvent, ReplaceEvent};
+use crate::t_file::TFile;
+use crate::t_mode::TMode;
+use crate::t_vim::{self, TAction, TMotion, TObject, TOperator, TParse, TRegister, TTarget, TVimCommand};
+use crate::t_file_explorer::TFileExplorer;
+use crate::t_options::TOptions;
+use crate::t_screen::screen;
+use crate::t_theme::{TColors, TTheme};
+use crate::t_window::{TLayout, TRect, TSplit, TWindow};
+
+
+pub struct TEditor
+{
+    pub buffers: Vec<TFile>,
+    pub windows: Vec<TWindow>,
+    pub layout: TLayout,
+    pub current: usize,
+    pub options: TOptions,
+    pub message: String,
+    pub command_line: TCommandLine,
+    pub search: String,
+    pub highlight: bool,
+    pub mode: TMode,
+    pub register: TRegister,
+    pub keymap: TKeymap,
+    pub theme: TTheme,
+    sequence: Vec<KeyEvent>,
+    pending: Vec<KeyEvent>,
+    change: Option<Vec<KeyEvent>>,
+    last_change: Vec<KeyEvent>,
+    macros: HashMap<char, Vec<KeyEvent>>,
+    recording: Option<(char, Vec<KeyEvent>)>,
+    last_macro: Option<char>,
+    replaying: usize,
+}
+
+
+impl TEditor
+{
+    pub fn build(paths: &[String], options: TOptions, keymap: TKeymap, theme: TTheme) -> Result<TEditor, &'static str>
+    {
+        let mut buffers = Vec::new();
+        for path in paths
+        {
+            if path == "-"
+            {
+                buffers.push(TFile::from_stdin(options.view)?);
+            }
+            else
+            {
+                buffers.push(open_file(path, options.view)?);
+            }
+        }
+
+        let window = TWindow { buffer: 0, index: 0, view: [0, 0], offset: 0, area: buffers[0].area };
+
+        let mut t_editor = TEditor { buffers,
+            windows: vec![window],
+            layout: TLayout::Window(0),
+            current: 0,
+            options,
+            message: String::new(),
+            command_line: TCommandLine::new(),
+            search: String::new(),
+            highlight: false,
+            mode: TMode::Normal,
+            register: TRegister::default(),
+            keymap,
+            theme,
+            sequence: Vec::new(),
+            pending: Vec::new(),
+            change: None,
+            last_change: Vec::new(),
+            macros: HashMap::new(),
+            recording: None,
+            last_macro: None,
+            replaying: 0,
+        };
+        t_editor.apply_options();
+
+        Ok(t_editor)
+    }
+
+    pub fn run(&mut self)
+    {
+        self.draw().unwrap();
+
+        loop
+        {
+            if self.options.autosave > 0 && !poll(Duration::from_secs(self.options.autosave)).unwrap()
+            {
+                self.autosave();
+                self.draw().unwrap();
+                continue;
+            }
+
+            match read().unwrap()
+            {
+                Event::Key(event) if event.kind == KeyEventKind::Press => {
+                    if !self.handle_key(event)
+                    {
+                        break;
+                    }
+                },
+                Event::Mouse(event) if event.kind == MouseEventKind::Down(MouseButton::Left) => {
+                    if event.row == 0 && self.options.tab_bar
+                    {
+                        self.click_tab(event.column);
+                    }
+                },
+                Event::Resize(_, _) => {},
+                _ => continue,
+            }
+            self.draw().unwrap();
+        }
+    }
+
+    fn buffer(&mut self) -> &mut TFile
+    {
+        &mut self.buffers[self.windows[self.current].buffer]
+    }
+
+    fn handle_key(&mut self, event: KeyEvent) -> bool
+    {
+        self.message.clear();
+
+        if let Some((_, keys)) = self.recording.as_mut().filter(|_| self.replaying == 0)
+        {
+            keys.push(event);
+        }
+
+        self.dispatch(event)
+    }
+
+    fn dispatch(&mut self, event: KeyEvent) -> bool
+    {
+        // vim's keys come before the global bindings in normal and visual mode
+        if self.options.modal && self.mode != TMode::Insert && self.command_line.prefix.is_none() && self.sequence.is_empty() && is_plain(&event)
+        {
+            return self.fallback(event);
+        }
+
+        let contexts: &[TContext] = if self.command_line.prefix.is_some()
+        {
+            &[TContext::Command]
+        }
+        else if self.options.modal
+        {
+            &[TContext::Global]
+        }
+        else if self.buffer().read_only
+        {
+            &[TContext::Global, TContext::View, TContext::Edit]
+        }
+        else
+        {
+            &[TContext::Global, TContext::Edit]
+        };
+
+        self.sequence.push(event);
+        match self.keymap.lookup(contexts, &self.sequence)
+        {
+            TLookup::Prefix => return true,
+            TLookup::Action(action) => {
+                self.sequence.clear();
+                return self.run_action(action);
+            },
+            TLookup::None => {
+                let sequence = std::mem::take(&mut self.sequence);
+                if sequence.len() > 1
+                {
+                    return self.fallback(sequence[0]) && sequence[1..].iter().all(|key| self.dispatch(*key));
+                }
+            },
+        }
+
+        self.fallback(event)
+    }
+
+    fn fallback(&mut self, event: KeyEvent) -> bool
+    {
+        if self.command_line.prefix.is_some()
+        {
+            self.command_line.handle_key(event);
+        }
+        else if self.options.modal
+        {
+            self.handle_mode_key(event);
+        }
+        else
+        {
+            self.edit(event);
+        }
+
+        true
+    }
+
+    fn run_action(&mut self, action: TKeyAction) -> bool
+    {
+        let buffer = self.windows[self.current].buffer;
+        let count = self.buffers.len();
+        let t_file = &mut self.buffers[buffer];
+
+        match action
+        {
+            TKeyAction::Quit => return !self.quit(),
+            TKeyAction::SaveAndExit => return !self.save_all(),
+
+            TKeyAction::Open => { self.open(); },
+            TKeyAction::NextBuffer => { self.switch((buffer + 1) % count); },
+            TKeyAction::PreviousBuffer => { self.switch((buffer + count - 1) % count); },
+            TKeyAction::PickBuffer => {
+                let picked = self.pick_buffer().unwrap_or(buffer);
+                self.switch(picked);
+            },
+
+            TKeyAction::Split => { self.split(TSplit::Horizontal); },
+            TKeyAction::VerticalSplit => { self.split(TSplit::Vertical); },
+            TKeyAction::CloseWindow => { self.close_window(); },
+            TKeyAction::NextWindow => { self.focus_window((self.current + 1) % self.windows.len()); },
+            TKeyAction::WindowLeft => { self.focus(-1, 0); },
+            TKeyAction::WindowDown => { self.focus(0, 1); },
+            TKeyAction::WindowUp => { self.focus(0, -1); },
+            TKeyAction::WindowRight => { self.focus(1, 0); },
+            TKeyAction::GrowHeight => { self.layout.resize(self.current, TSplit::Horizontal, 5); },
+            TKeyAction::ShrinkHeight => { self.layout.resize(self.current, TSplit::Horizontal, -5); },
+            TKeyAction::GrowWidth => { self.layout.resize(self.current, TSplit::Vertical, 5); },
+            TKeyAction::ShrinkWidth => { self.layout.resize(self.current, TSplit::Vertical, -5); },
+            TKeyAction::Equalize => { self.layout.equalize(); },
+
+            TKeyAction::CommandLine => { self.command_line.open(':'); },
+            TKeyAction::Search => { self.command_line.open('/'); },
+            TKeyAction::NextMatch => { self.search_next(true); },
+            TKeyAction::PreviousMatch => { self.search_next(false); },
+            TKeyAction::Run => return self.submit_prompt(),
+            TKeyAction::Cancel => { self.command_line.cancel(); },
+            TKeyAction::HistoryBack => { self.command_line.step_history(-1); },
+            TKeyAction::HistoryForward => { self.command_line.step_history(1); },
+            TKeyAction::Complete => { self.command_line.complete(1); },
+            TKeyAction::CompleteBack => { self.command_line.complete(-1); },
+            TKeyAction::Record => { self.toggle_recording('q'); },
+            TKeyAction::Replay => return self.replay('q', 1),
+            TKeyAction::MatchBracket => {
+                match t_file.match_bracket(t_file.index)
+                {
+                    Some(target) => t_file.move_to(target).unwrap(),
+                    None => { self.message = String::from("No matching bracket."); },
+                }
+            },
+            TKeyAction::ExpandSelection | TKeyAction::ShrinkSelection => { self.expand_selection(action == TKeyAction::ExpandSelection); },
+
+            TKeyAction::Up => { t_file.add_event(MoveEvent(Direction::Up, 0)); },
+            TKeyAction::Down => { t_file.add_event(MoveEvent(Direction::Down, 0)); },
+            TKeyAction::Left => { t_file.add_event(MoveEvent(Direction::Left, 0)); },
+            TKeyAction::Right => { t_file.add_event(MoveEvent(Direction::Right, 0)); },
+            TKeyAction::PageUp => { t_file.add_event(MoveEvent(Direction::PageUp, 0)); },
+            TKeyAction::PageDown => { t_file.add_event(MoveEvent(Direction::PageDown, 0)); },
+            TKeyAction::Undo => { t_file.undo(); },
+
+            _ if t_file.read_only => {},
+
+            TKeyAction::Newline => {
+                let newline = if self.options.auto_indent { t_file.newline(&t_file.indent_unit(&self.options)) } else { String::from("\n") };
+                t_file.add_event(InsertEvent(newline, 0));
+            },
+            TKeyAction::Backspace => {
+                let units = t_file.outdent(&t_file.indent_unit(&self.options)).unwrap_or(1);
+                t_file.add_event(DeleteEvent(units, String::new(), 0));
+            },
+            TKeyAction::Indent => { t_file.add_event(InsertEvent(t_file.indent(&t_file.indent_unit(&self.options)), 0)); },
+
+            TKeyAction::Select | TKeyAction::Parent => {},
+        }
+
+        true
+    }
+
+    fn expand_selection(&mut self, expand: bool)
+    {
+        if !self.options.modal
+        {
+            self.message = String::from("Selections need modal editing.");
+            return;
+        }
+
+        if self.mode != TMode::Visual
+        {
+            if !expand
+            {
+                return;
+            }
+            self.set_mode(TMode::Visual);
+        }
+
+        let t_file = self.buffer();
+        let changed = if expand { t_file.expand_selection() } else { t_file.shrink_selection() };
+        if !changed.unwrap()
+        {
+            self.message = String::from(if expand { "Nothing to expand to." } else { "Nothing to shrink to." });
+        }
+    }
+
+    fn handle_mode_key(&mut self, event: KeyEvent)
+    {
+        match self.mode
+        {
+            TMode::Normal => self.handle_normal(event),
+            TMode::Insert => self.handle_insert(event),
+            TMode::Visual => self.handle_visual(event),
+        }
+    }
+
+    fn set_mode(&mut self, mode: TMode)
+    {
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        t_file.anchor = if mode == TMode::Visual { Some(t_file.index) } else { None };
+
+        if mode == TMode::Normal
+        {
+            t_file.end_group();
+            if let Some(keys) = self.change.take()
+            {
+                self.last_change = keys;
+            }
+        }
+
+        self.mode = mode;
+    }
+
+    fn arrow(&mut self, event: KeyEvent) -> bool
+    {
+        let t_file = self.buffer();
+
+        match event.code
+        {
+            KeyCode::Left => t_file.move_left(1),
+            KeyCode::Right => t_file.move_right(1),
+            KeyCode::Up => t_file.move_up(),
+            KeyCode::Down => t_file.move_down(),
+            KeyCode::PageUp => t_file.page_up(),
+            KeyCode::PageDown => t_file.page_down(),
+            _ => return false,
+        }.unwrap();
+
+        true
+    }
+
+    fn pending_command(&mut self, event: KeyEvent) -> Option<(TVimCommand, Vec<KeyEvent>)>
+    {
+        if !matches!(event.code, KeyCode::Char(_)) || event.modifiers.contains(KeyModifiers::CONTROL)
+        {
+            self.pending.clear();
+            return None;
+        }
+
+        self.pending.push(event);
+        let keys: Vec<char> = self.pending.iter()
+            .filter_map(|e| if let KeyCode::Char(c) = e.code { Some(c) } else { None })
+            .collect();
+
+        match t_vim::parse(&keys)
+        {
+            TParse::Incomplete => None,
+            TParse::Invalid => {
+                self.pending.clear();
+                None
+            },
+            TParse::Complete(command) => Some((command, std::mem::take(&mut self.pending))),
+        }
+    }
+
+    fn handle_normal(&mut self, event: KeyEvent)
+    {
+        if self.pending.is_empty() && self.arrow(event)
+        {
+            return;
+        }
+
+        if event.code == KeyCode::Esc
+        {
+            self.pending.clear();
+            return;
+        }
+
+        if self.recording.is_some() && self.pending.is_empty() && event.code == KeyCode::Char('q')
+        {
+            self.toggle_recording('q');
+            return;
+        }
+
+        if let Some((command, keys)) = self.pending_command(event)
+        {
+            self.run_vim(command, keys);
+        }
+    }
+
+    fn run_vim(&mut self, command: TVimCommand, keys: Vec<KeyEvent>)
+    {
+        let count = command.count.unwrap_or(1);
+        let changes = match command.action
+        {
+            TAction::Move(_) | TAction::Repeat | TAction::Record(_) | TAction::Replay(_) => false,
+            TAction::Mark(_) | TAction::JumpMark(_, _) => false,
+            TAction::Operate(TOperator::Yank, _) => false,
+            TAction::Operate(_, _) => true,
+            TAction::Key(key) => "xpPiaIAoO".contains(key),
+        };
+
+        if changes
+        {
+            if self.buffer().read_only
+            {
+                self.message = String::from("File is read-only.");
+                return;
+            }
+            self.change = Some(keys);
+            self.buffer().begin_group();
+        }
+
+        match command.action
+        {
+            TAction::Move(motion) => { self.move_by(motion, command.count); },
+            TAction::Operate(operator, target) => { self.operate(operator, target, command.count); },
+            TAction::Key(key) => { self.normal_key(key, count); },
+            TAction::Repeat => {
+                let keys = self.last_change.clone();
+                for _ in 0..count
+                {
+                    keys.iter().for_each(|key| self.handle_mode_key(*key));
+                }
+            },
+            TAction::Record(register) if register.is_ascii_alphanumeric() => { self.toggle_recording(register); },
+            TAction::Replay('@') => {
+                match self.last_macro
+                {
+                    Some(register) => { self.replay(register, count); },
+                    None => { self.message = String::from("No previous macro."); },
+                }
+            },
+            TAction::Replay(register) => { self.replay(register, count); },
+            TAction::Record(_) => { self.message = String::from("Invalid register."); },
+            TAction::Mark(name) => {
+                if let Err(err) = self.set_mark(name)
+                {
+                    self.message = err.to_string();
+                }
+            },
+            TAction::JumpMark(name, exact) => {
+                if let Err(err) = self.jump_mark(name, exact)
+                {
+                    self.message = err.to_string();
+                }
+            },
+        }
+
+        if changes && self.mode == TMode::Normal
+        {
+            self.set_mode(TMode::Normal);
+        }
+    }
+
+    fn move_by(&mut self, motion: TMotion, count: Option<usize>)
+    {
+        let t_file = self.buffer();
+
+        match motion
+        {
+            TMotion::Up | TMotion::Down => {
+                for _ in 0..count.unwrap_or(1)
+                {
+                    if motion == TMotion::Up { t_file.move_up() } else { t_file.move_down() }.unwrap();
+                }
+            },
+            TMotion::FirstLine | TMotion::LastLine => {
+                let line = match motion
+                {
+                    TMotion::FirstLine => count.unwrap_or(1),
+                    _ => count.unwrap_or(t_file.line_count()),
+                };
+                t_file.goto_line(line).unwrap();
+                t_file.move_to(t_vim::first_non_blank(&t_file.content, t_file.index)).unwrap();
+            },
+            TMotion::MatchBracket => {
+                if let Some(target) = t_file.match_bracket(t_file.index)
+                {
+                    t_file.move_to(target).unwrap();
+                }
+            },
+            _ => {
+                if let Some(target) = t_vim::motion_target(&t_file.content, t_file.index, motion, count)
+                {
+                    t_file.move_to(target).unwrap();
+                }
+            },
+        }
+    }
+
+    fn operate(&mut self, operator: TOperator, target: TTarget, count: Option<usize>)
+    {
+        let t_file = self.buffer();
+        let index = t_file.index;
+        let brackets = match target
+        {
+            TTarget::Motion(TMotion::MatchBracket) => t_file.match_bracket(index).map(|b| [index.min(b), index.max(b)]),
+            TTarget::Object(TObject::Pair(open, _)) => t_file.enclosing_pair(index, open),
+            _ => None,
+        };
+        let content = t_file.content.as_str();
+
+        let range = match target
+        {
+            TTarget::Motion(TMotion::MatchBracket) => brackets.map(|[first, last]| ([first, last + 1], false)),
+            TTarget::Object(TObject::Pair(_, around)) => brackets.map(|[first, last]| if around { ([first, last + 1], false) } else { ([first + 1, last], false) }),
+            TTarget::Line => {
+                let last = match count
+                {
+                    Some(n) if n > 1 => t_vim::motion_target(content, index, TMotion::Down, Some(n - 1)).unwrap_or(index),
+                    _ => index,
+                };
+                Some((t_vim::line_span(content, index, last), true))
+            },
+            TTarget::Motion(motion) => t_vim::motion_range(content, index, motion, count, operator == TOperator::Change),
+            TTarget::Object(object) => t_vim::object_range(content, index, object),
+        };
+
+        if let Some((range, linewise)) = range
+        {
+            self.apply(operator, range, linewise);
+        }
+    }
+
+    fn apply(&mut self, operator: TOperator, range: [usize; 2], linewise: bool)
+    {
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        let content = t_file.content.as_str();
+        let index = t_file.index;
+
+        let start = if linewise && range[1] == content.len() && content[range[0]..].starts_with('\n')
+        {
+            range[0] + 1
+        }
+        else
+        {
+            range[0]
+        };
+
+        let mut text = content[range[0]..range[1]].to_string();
+        if linewise && !text.ends_with('\n')
+        {
+            text = format!("{}\n", &text[start - range[0]..]);
+        }
+
+        match operator
+        {
+            TOperator::Yank => {
+                self.register = TRegister { text, linewise };
+                if !linewise || !(start..range[1]).contains(&index)
+                {
+                    t_file.move_to(start).unwrap();
+                }
+            },
+            TOperator::Delete => {
+                if range[0] < range[1]
+                {
+                    self.register = TRegister { text, linewise };
+                    t_file.add_event(ReplaceEvent(range, String::new(), String::new()));
+                }
+                if linewise
+                {
+                    let index = range[0].min(t_file.content.len());
+                    t_file.move_to(t_vim::first_non_blank(&t_file.content, index)).unwrap();
+                }
+            },
+            TOperator::Change => {
+                let end = if linewise && content[..range[1]].ends_with('\n') { range[1] - 1 } else { range[1] };
+                let range = if linewise { [start, end] } else { range };
+                self.register = TRegister { text, linewise };
+                if range[0] < range[1]
+                {
+                    t_file.add_event(ReplaceEvent(range, String::new(), String::new()));
+                }
+                t_file.move_to(range[0]).unwrap();
+                self.set_mode(TMode::Insert);
+            },
+            TOperator::Indent | TOperator::Dedent => {
+                let end = t_vim::line_end(content, range[1].saturating_sub(1).max(start));
+                let lines = t_vim::shift_lines(&content[start..end], t_file.indent_unit(&self.options).as_str(), operator == TOperator::Indent);
+                t_file.add_event(ReplaceEvent([start, end], lines, String::new()));
+                t_file.move_to(t_vim::first_non_blank(&t_file.content, start)).unwrap();
+            },
+        }
+    }
+
+    fn normal_key(&mut self, key: char, count: usize)
+    {
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        let [start, end] = t_file.line_range();
+        let index = t_file.index;
+        let next = t_file.content[index..].chars().next();
+
+        match key
+        {
+            ':' => { self.command_line.open(':'); },
+            '/' => { self.command_line.open('/'); },
+            'n' => { self.search_next(true); },
+            'N' => { self.search_next(false); },
+            'u' => { (0..count).for_each(|_| t_file.undo()); },
+            'v' => { self.set_mode(TMode::Visual); },
+            'i' => { self.set_mode(TMode::Insert); },
+            'a' => {
+                if next.is_some_and(|c| c != '\n')
+                {
+                    t_file.move_right(1).unwrap();
+                }
+                self.set_mode(TMode::Insert);
+            },
+            'I' => {
+                t_file.move_to(t_vim::first_non_blank(&t_file.content, index)).unwrap();
+                self.set_mode(TMode::Insert);
+            },
+            'A' => {
+                t_file.move_to(end).unwrap();
+                self.set_mode(TMode::Insert);
+            },
+            'o' => {
+                t_file.move_to(end).unwrap();
+                let newline = if self.options.auto_indent { t_file.newline(&t_file.indent_unit(&self.options)) } else { String::from("\n") };
+                t_file.add_event(InsertEvent(newline, 0));
+                self.set_mode(TMode::Insert);
+            },
+            'O' => {
+                let indent = if self.options.auto_indent { t_file.line_indent(start).to_string() } else { String::new() };
+                t_file.move_to(start).unwrap();
+                t_file.add_event(InsertEvent(format!("{indent}\n"), 0));
+                t_file.move_to(start + indent.len()).unwrap();
+                self.set_mode(TMode::Insert);
+            },
+            'x' => {
+                let last = t_file.content[index..end].char_indices().nth(count).map_or(end, |(i, _)| index + i);
+                if last > index
+                {
+                    self.register = TRegister { text: t_file.content[index..last].to_string(), linewise: false };
+                    t_file.add_event(ReplaceEvent([index, last], String::new(), String::new()));
+                }
+            },
+            'p' | 'P' => { self.put(key == 'p', count); },
+            _ => {},
+        }
+    }
+
+    fn put(&mut self, after: bool, count: usize)
+    {
+        if self.register.text.is_empty()
+        {
+            return;
+        }
+
+        let text = self.register.text.repeat(count);
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        let [start, end] = t_file.line_range();
+
+        if !self.register.linewise
+        {
+            if after && t_file.content[t_file.index..].starts_with(|c: char| c != '\n')
+            {
+                t_file.move_right(1).unwrap();
+            }
+            t_file.add_event(InsertEvent(text, 0));
+            t_file.move_left(1).unwrap();
+            return;
+        }
+
+        let line = if !after
+        {
+            t_file.move_to(start).unwrap();
+            t_file.add_event(InsertEvent(text, 0));
+            start
+        }
+        else if end == t_file.content.len()
+        {
+            t_file.move_to(end).unwrap();
+            t_file.add_event(InsertEvent(format!("\n{}", text.trim_end_matches('\n')), 0));
+            end + 1
+        }
+        else
+        {
+            t_file.move_to(end + 1).unwrap();
+            t_file.add_event(InsertEvent(text, 0));
+            end + 1
+        };
+
+        t_file.move_to(t_vim::first_non_blank(&t_file.content, line)).unwrap();
+    }
+
+    fn handle_insert(&mut self, event: KeyEvent)
+    {
+        if let Some(keys) = self.change.as_mut()
+        {
+            keys.push(event);
+        }
+
+        match event.code
+        {
+            KeyCode::Esc => {
+                self.set_mode(TMode::Normal);
+                let t_file = self.buffer();
+                if t_file.content[..t_file.index].chars().next_back().is_some_and(|c| c != '\n')
+                {
+                    t_file.move_left(1).unwrap();
+                }
+            },
+            _ => match self.keymap.lookup(&[TContext::Edit], &[event])
+            {
+                TLookup::Action(action) if action != TKeyAction::Quit => { self.run_action(action); },
+                _ => { self.edit(event); },
+            },
+        }
+    }
+
+    fn handle_visual(&mut self, event: KeyEvent)
+    {
+        if self.pending.is_empty() && self.arrow(event)
+        {
+            return;
+        }
+
+        let t_file = self.buffer();
+        let range = t_file.selection().unwrap_or([t_file.index, t_file.index]);
+        let read_only = t_file.read_only;
+
+        if self.pending.is_empty()
+        {
+            let operator = match event.code
+            {
+                KeyCode::Esc | KeyCode::Char('v') => {
+                    self.set_mode(TMode::Normal);
+                    return;
+                },
+                KeyCode::Char(':') => {
+                    self.command_line.open(':');
+                    return;
+                },
+                KeyCode::Char('o') => {
+                    let t_file = self.buffer();
+                    if let Some(anchor) = t_file.anchor
+                    {
+                        t_file.anchor = Some(t_file.index);
+                        t_file.move_to(anchor).unwrap();
+                    }
+                    return;
+                },
+                KeyCode::Char('y') => Some(TOperator::Yank),
+                KeyCode::Char('d' | 'x') => Some(TOperator::Delete),
+                KeyCode::Char('c') => Some(TOperator::Change),
+                KeyCode::Char('>') => Some(TOperator::Indent),
+                KeyCode::Char('<') => Some(TOperator::Dedent),
+                _ => None,
+            };
+
+            if let Some(operator) = operator
+            {
+                if read_only && operator != TOperator::Yank
+                {
+                    self.message = String::from("File is read-only.");
+                    return;
+                }
+                self.buffer().begin_group();
+                self.buffer().anchor = None;
+                self.apply(operator, range, false);
+                if self.mode == TMode::Visual
+                {
+                    self.set_mode(TMode::Normal);
+                }
+                return;
+            }
+        }
+
+        if let Some((command, _)) = self.pending_command(event)
+        {
+            if let TAction::Move(motion) = command.action
+            {
+                self.move_by(motion, command.count);
+            }
+        }
+    }
+
+    fn toggle_recording(&mut self, register: char)
+    {
+        match self.recording.take()
+        {
+            Some((register, mut keys)) => {
+                keys.pop();
+                self.macros.insert(register, keys);
+                self.message = format!("Recorded @{register}");
+            },
+            None => { self.recording = Some((register, Vec::new())); },
+        }
+    }
+
+    fn replay(&mut self, register: char, count: usize) -> bool
+    {
+        let keys = match self.macros.get(&register)
+        {
+            None => {
+                self.message = format!("Register {register} is empty.");
+                return true;
+            },
+            Some(keys) => keys.clone(),
+        };
+
+        if self.replaying > 100
+        {
+            self.message = String::from("Macro recursion too deep.");
+            return true;
+        }
+
+        self.last_macro = Some(register);
+        self.replaying += 1;
+        let buffer = self.windows[self.current].buffer;
+        self.buffers[buffer].begin_group();
+
+        let mut running = true;
+        'replay: for _ in 0..count
+        {
+            for key in keys.iter()
+            {
+                if !self.handle_key(*key)
+                {
+                    running = false;
+                    break 'replay;
+                }
+            }
+        }
+
+        self.buffers[buffer].end_group();
+        self.replaying -= 1;
+
+        running
+    }
+
+    fn save_macro(&mut self, args: &str) -> Result<(), &'static str>
+    {
+        let mut args = args.split_whitespace();
+        let (Some(register), Some(name), None) = (args.next(), args.next(), args.next()) else {
+            return Err("Usage: msave REGISTER NAME");
+        };
+
+        let keys = register.chars().next()
+            .filter(|_| register.chars().count() == 1)
+            .and_then(|r| self.macros.get(&r))
+            .ok_or("Register is empty.")?;
+        let notation = format_keys(keys)?;
+        let path = macro_path(name)?;
+
+        let saved = fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, format!("{notation}\n")));
+        if saved.is_err()
+        {
+            return Err("Cannot write macro.");
+        }
+
+        self.message = format!("Saved @{register} as {name}");
+
+        Ok(())
+    }
+
+    fn load_macro(&mut self, args: &str) -> Result<(), &'static str>
+    {
+        let mut args = args.split_whitespace();
+        let (Some(name), Some(register), None) = (args.next(), args.next(), args.next()) else {
+            return Err("Usage: mload NAME REGISTER");
+        };
+
+        let register = register.chars().next()
+            .filter(|r| register.chars().count() == 1 && r.is_ascii_alphanumeric())
+            .ok_or("Invalid register.")?;
+        let notation = fs::read_to_string(macro_path(name)?).map_err(|_| "Cannot read macro.")?;
+        let keys = parse_keys(notation.trim_end_matches(['\n', '\r']))?;
+        self.macros.insert(register, keys);
+
+        self.message = format!("Loaded {name} into @{register}");
+
+        Ok(())
+    }
+
+    fn set_mark(&mut self, name: char) -> Result<(), &'static str>
+    {
+        if !name.is_ascii_alphabetic()
+        {
+            return Err("Invalid mark.");
+        }
+
+        let t_file = self.buffer();
+        if t_file.large.is_some()
+        {
+            return Err("Marks are not available in large files.");
+        }
+        t_file.set_mark(name);
+
+        self.message = format!("Mark {name} set");
+
+        Ok(())
+    }
+
+    fn jump_mark(&mut self, name: char, exact: bool) -> Result<(), &'static str>
+    {
+        let t_file = self.buffer();
+        let offset = *t_file.marks.get(&name).ok_or("Mark not set.")?;
+
+        t_file.move_to(offset).unwrap();
+        if !exact
+        {
+            t_file.move_to(t_vim::first_non_blank(&t_file.content, t_file.index)).unwrap();
+        }
+
+        Ok(())
+    }
+
+    fn set_indent(&mut self, args: &str) -> Result<(), &'static str>
+    {
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        match args
+        {
+            "" => {},
+            "auto" => { t_file.indent_override = None; },
+            "tabs" => { t_file.indent_override = Some(String::from("\t")); },
+            width => {
+                let width = width.parse().ok().filter(|w| (1..=16).contains(w)).ok_or("Usage: indent [tabs|N|auto]")?;
+                t_file.indent_override = Some(" ".repeat(width));
+            },
+        }
+
+        let source = if t_file.indent_override.is_some()
+        {
+            "set"
+        }
+        else if t_file.editorconfig.indent_style.is_some()
+        {
+            ".editorconfig"
+        }
+        else if t_file.detected_indent.is_some() && self.options.detect_indent
+        {
+            "detected"
+        }
+        else
+        {
+            "default"
+        };
+        self.message = format!("Indenting with {} ({source}).", describe_indent(&t_file.indent_unit(&self.options)));
+
+        Ok(())
+    }
+
+    fn list_marks(&mut self)
+    {
+        let t_file = self.buffer();
+        let marks: Vec<String> = t_file.marks.iter().map(|(name, offset)| {
+            let (line, column) = t_file.mark_position(*offset);
+            format!("{name} {}:{}", line + 1, column + 1)
+        }).collect();
+
+        self.message = if marks.is_empty() { String::from("No marks set.") } else { marks.join("  ") };
+    }
+
+    fn submit_prompt(&mut self) -> bool
+    {
+        let prefix = self.command_line.prefix;
+
+        match self.command_line.submit()
+        {
+            Some(input) if prefix == Some('/') => {
+                if !input.is_empty()
+                {
+                    self.search = input;
+                }
+                self.highlight = true;
+                self.search_next(true);
+                true
+            },
+            Some(input) => self.execute(input.as_str()),
+            None => true,
+        }
+    }
+
+    pub fn execute(&mut self, input: &str) -> bool
+    {
+        match parse(input).and_then(|call| self.run_command(call))
+        {
+            Ok(running) => running,
+            Err(err) => {
+                self.message = err.to_string();
+                true
+            },
+        }
+    }
+
+    fn run_command(&mut self, call: TCommandCall) -> Result<bool, &'static str>
+    {
+        let buffer = self.windows[self.current].buffer;
+        let count = self.buffers.len();
+
+        match call.command
+        {
+            TCommandName::Write => { self.write(call.args.as_str())?; },
+            TCommandName::Quit => return self.quit_window(call.bang),
+            TCommandName::WriteQuit => {
+                self.write(call.args.as_str())?;
+                return self.quit_window(call.bang);
+            },
+            TCommandName::Exit => {
+                if self.buffer().modified
+                {
+                    self.write(call.args.as_str())?;
+                }
+                return self.quit_window(call.bang);
+            },
+            TCommandName::Edit => {
+                if call.args.is_empty()
+                {
+                    return Err("No file name.");
+                }
+                self.open_path(call.args.as_str());
+            },
+            TCommandName::Substitute => { self.substitute(call.range, call.args.as_str())?; },
+            TCommandName::Set => { self.set(call.args.as_str())?; },
+            TCommandName::Goto => {
+                let t_file = self.buffer();
+                let line = match call.args.as_str()
+                {
+                    "$" => t_file.line_count(),
+                    n => n.parse().unwrap_or(usize::MAX),
+                };
+                if t_file.goto_line(line).is_err()
+                {
+                    return Err("Problem reading file.");
+                }
+            },
+            TCommandName::BufferNext => { self.switch((buffer + 1) % count); },
+            TCommandName::BufferPrevious => { self.switch((buffer + count - 1) % count); },
+            TCommandName::Split | TCommandName::VerticalSplit => {
+                let split = if call.command == TCommandName::Split { TSplit::Horizontal } else { TSplit::Vertical };
+                self.split(split);
+                if !call.args.is_empty()
+                {
+                    self.open_path(call.args.as_str());
+                }
+            },
+            TCommandName::Close => {
+                if self.windows.len() == 1
+                {
+                    return Err("Cannot close last window.");
+                }
+                self.close_window();
+            },
+            TCommandName::NoHighlight => { self.highlight = false; },
+            TCommandName::MacroSave => { self.save_macro(call.args.as_str())?; },
+            TCommandName::MacroLoad => { self.load_macro(call.args.as_str())?; },
+            TCommandName::Mark => {
+                let mut names = call.args.chars();
+                match (names.next(), names.next())
+                {
+                    (Some(name), None) => { self.set_mark(name)?; },
+                    _ => return Err("Usage: mark NAME"),
+                }
+            },
+            TCommandName::Marks => { self.list_marks(); },
+            TCommandName::Indent => { self.set_indent(call.args.as_str())?; },
+            TCommandName::DeleteMarks => {
+                if !call.bang && call.args.is_empty()
+                {
+                    return Err("Usage: delmarks NAMES or delmarks!");
+                }
+                let names: String = if call.bang { self.buffer().marks.keys().collect() } else { call.args };
+                self.buffer().delete_marks(names.as_str());
+            },
+            TCommandName::GotoMark => {
+                let mut names = call.args.chars();
+                match (names.next(), names.next())
+                {
+                    (Some(name), None) => { self.jump_mark(name, false)?; },
+                    _ => return Err("Usage: 'NAME"),
+                }
+            },
+        }
+
+        Ok(true)
+    }
+
+    fn write(&mut self, path: &str) -> Result<(), &'static str>
+    {
+        let t_file = self.buffer();
+        if t_file.read_only
+        {
+            return Err("File is read-only.");
+        }
+
+        let result = if path.is_empty()
+        {
+            if t_file.file.is_none()
+            {
+                return Err("No file name.");
+            }
+            t_file.write()
+        }
+        else
+        {
+            t_file.write_to(path)
+        };
+
+        if result.is_err()
+        {
+            return Err("Cannot write file.");
+        }
+
+        let buffer = self.windows[self.current].buffer;
+        self.configure(buffer);
+        self.message = format!("\"{}\" written", self.buffer().name());
+
+        Ok(())
+    }
+
+    fn quit_window(&mut self, force: bool) -> Result<bool, &'static str>
+    {
+        if self.windows.len() > 1
+        {
+            self.close_window();
+            return Ok(true);
+        }
+
+        if !force && self.buffers.iter().any(|t_file| t_file.is_unsaved())
+        {
+            return Err("Unsaved changes, add ! to quit anyway.");
+        }
+
+        Ok(false)
+    }
+
+    fn substitute(&mut self, range: TRange, args: &str) -> Result<(), &'static str>
+    {
+        let (pattern, replacement, global) = parse_substitute(args)?;
+
+        let t_file = self.buffer();
+        if t_file.read_only
+        {
+            return Err("File is read-only.");
+        }
+
+        let range = match range
+        {
+            TRange::Line => t_file.line_range(),
+            TRange::All => [0, t_file.content.len()],
+        };
+
+        // without g only the first match of each line is replaced
+        let content = &t_file.content[range[0]..range[1]];
+        let mut text = String::new();
+        let (mut count, mut copied, mut line_end) = (0, 0, 0);
+        for (i, found) in content.match_indices(pattern.as_str())
+        {
+            if !global && i < line_end
+            {
+                continue;
+            }
+
+            text.push_str(&content[copied..i]);
+            text.push_str(replacement.as_str());
+            copied = i + found.len();
+            count += 1;
+            line_end = match pattern.contains('\n')
+            {
+                true => content.len(),
+                false => content[i..].find('\n').map_or(content.len(), |n| i + n),
+            };
+        }
+        text.push_str(&content[copied..]);
+
+        if count == 0
+        {
+            return Err("Pattern not found.");
+        }
+
+        t_file.add_event(ReplaceEvent(range, text, String::new()));
+
+        self.message = match count
+        {
+            1 => String::from("1 substitution"),
+            n => format!("{n} substitutions"),
+        };
+
+        Ok(())
+    }
+
+    fn set(&mut self, args: &str) -> Result<(), &'static str>
+    {
+        if args.is_empty()
+        {
+            self.message = self.options.show();
+            return Ok(());
+        }
+
+        let tab_bar = self.options.tab_bar;
+        let modal = self.options.modal;
+        for arg in args.split_whitespace()
+        {
+            let (name, value) = match arg.split_once('=')
+            {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg, None),
+            };
+            self.options.set(name, value)?;
+        }
+        self.apply_options();
+
+        if self.options.modal != modal
+        {
+            self.set_mode(TMode::Normal);
+        }
+
+        if self.options.theme != self.theme.name
+        {
+            match TTheme::load(self.options.theme.as_str(), self.options.colors.unwrap_or_else(TColors::detect))
+            {
+                Ok(theme) => { self.theme = theme; },
+                Err(err) => {
+                    self.options.theme = self.theme.name.clone();
+                    self.message = err;
+                },
+            }
+        }
+
+        if self.options.tab_bar != tab_bar
+        {
+            if self.options.tab_bar
+            {
+                execute!(screen(), EnableMouseCapture).unwrap();
+            }
+            else
+            {
+                execute!(screen(), DisableMouseCapture).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn search_next(&mut self, forward: bool)
+    {
+        if self.search.is_empty()
+        {
+            self.message = String::from("No previous search.");
+            return;
+        }
+
+        self.highlight = true;
+        let search = self.search.clone();
+        let t_file = self.buffer();
+        match t_file.find(search.as_str(), forward)
+        {
+            Some(index) => { t_file.move_to(index).unwrap(); },
+            None => { self.message = format!("Pattern not found: {search}"); },
+        }
+    }
+
+    fn edit(&mut self, event: KeyEvent)
+    {
+        let auto_indent = self.options.auto_indent;
+        let t_file = &mut self.buffers[self.windows[self.current].buffer];
+        let unit = t_file.indent_unit(&self.options);
+
+        match event.code
+        {
+            _ if t_file.read_only => {},
+            _ if event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {},
+
+            KeyCode::Char(' ') => { t_file.add_event(InsertEvent(String::from(" "), 0)); },
+            KeyCode::Char(c) if auto_indent && t_file.dedent(c, &unit).is_some() => {
+                let range = t_file.dedent(c, &unit).unwrap();
+                t_file.begin_group();
+                t_file.add_event(ReplaceEvent(range, String::new(), String::new()));
+                t_file.add_event(InsertEvent(c.to_string(), 0));
+                t_file.end_group();
+            },
+            _ => {
+                if event.code.to_string().len() == 1
+                {
+                    t_file.add_event(InsertEvent(event.code.to_string(), 0));
+                }
+            },
+        }
+    }
+
+    fn switch(&mut self, index: usize)
+    {
+        self.leave_visual();
+        self.store();
+
+        let window = &mut self.windows[self.current];
+        window.buffer = index;
+        window.index = self.buffers[index].index;
+        window.view = self.buffers[index].view;
+        window.offset = self.buffers[index].offset;
+
+        self.load();
+    }
+
+    fn split(&mut self, split: TSplit)
+    {
+        self.store();
+
+        let window = &self.windows[self.current];
+        let new = TWindow { buffer: window.buffer, index: window.index, view: window.view, offset: window.offset, area: window.area };
+        self.windows.push(new);
+        self.layout.split(self.current, split, self.windows.len() - 1);
+        self.current = self.windows.len() - 1;
+    }
+
+    fn close_window(&mut self)
+    {
+        if self.windows.len() == 1
+        {
+            return;
+        }
+
+        self.layout.close(self.current);
+        self.layout.renumber(self.current);
+        self.windows.remove(self.current);
+        self.current = self.current.min(self.windows.len() - 1);
+
+        self.load();
+    }
+
+    fn focus(&mut self, dx: i32, dy: i32)
+    {
+        let area = self.windows[self.current].area;
+        let x = match dx
+        {
+            -1 => area.x as i32 - 2,
+            1 => (area.x + area.width) as i32 + 1,
+            _ => (area.x + area.width / 2) as i32,
+        };
+        let y = match dy
+        {
+            -1 => area.y as i32 - 2,
+            1 => (area.y + area.height) as i32 + 1,
+            _ => (area.y + area.height / 2) as i32,
+        };
+
+        if x < 0 || y < 0
+        {
+            return;
+        }
+
+        if let Some(i) = self.windows.iter().position(|w| w.area.contains(x as u16, y as u16))
+        {
+            self.focus_window(i);
+        }
+    }
+
+    fn focus_window(&mut self, index: usize)
+    {
+        self.leave_visual();
+        self.store();
+        self.current = index;
+        self.load();
+    }
+
+    fn leave_visual(&mut self)
+    {
+        if self.mode == TMode::Visual
+        {
+            self.set_mode(TMode::Normal);
+        }
+    }
+
+    fn store(&mut self)
+    {
+        let window = &mut self.windows[self.current];
+        let t_file = &self.buffers[window.buffer];
+        window.index = t_file.index;
+        window.view = t_file.view;
+        window.offset = t_file.offset;
+    }
+
+    fn load(&mut self)
+    {
+        let window = &self.windows[self.current];
+        let t_file = &mut self.buffers[window.buffer];
+        t_file.restore(window.index, window.view, window.offset, window.area).unwrap();
+    }
+
+    fn configure(&mut self, buffer: usize)
+    {
+        let t_file = &mut self.buffers[buffer];
+        t_file.numbers = self.options.numbers;
+        t_file.undo_limit = self.options.undo_limit;
+        t_file.tab_width = t_file.editorconfig.tab_width().unwrap_or(self.options.tab_width);
+        t_file.wrap = self.options.wrap;
+        t_file.word_wrap = self.options.word_wrap;
+        t_file.wrap_indent = self.options.wrap_indent;
+        t_file.wrap_marker = self.options.wrap_marker.clone();
+        t_file.visual_rows = self.options.visual_rows;
+        t_file.colors = self.options.syntax;
+        t_file.relayout().unwrap();
+    }
+
+    fn apply_options(&mut self)
+    {
+        for buffer in 0..self.buffers.len()
+        {
+            self.configure(buffer);
+        }
+    }
+
+    fn draw(&mut self) -> std::io::Result<()>
+    {
+        self.store();
+
+        let (cols, rows) = size()?;
+        let top = if self.options.tab_bar { 1 } else { 0 };
+        let mut separators = Vec::new();
+        self.layout.arrange(TRect { x: 0, y: top, width: cols, height: rows.saturating_sub(top + 1) }, &mut self.windows, &mut separators);
+
+        let mut screen = BufWriter::new(screen());
+
+        if self.options.tab_bar
+        {
+            self.draw_tabs(&mut screen, cols)?;
+        }
+
+        let order = (0..self.windows.len()).filter(|i| *i != self.current).chain([self.current]);
+        for i in order
+        {
+            let window = &mut self.windows[i];
+            let t_file = &mut self.buffers[window.buffer];
+            t_file.highlight = if self.highlight { self.search.clone() } else { String::new() };
+            // only a shared buffer or a resized window needs its layout redone
+            if (t_file.index, t_file.view, t_file.offset, t_file.area) != (window.index, window.view, window.offset, window.area)
+            {
+                t_file.restore(window.index, window.view, window.offset, window.area)?;
+            }
+            t_file.draw(&mut screen, &self.theme)?;
+            window.index = t_file.index;
+            window.view = t_file.view;
+            window.offset = t_file.offset;
+        }
+
+        for (split, area) in separators
+        {
+            let line = match split
+            {
+                TSplit::Horizontal => "─".repeat(area.width as usize),
+                TSplit::Vertical => String::from("│"),
+            };
+            for y in area.y..area.y + area.height
+            {
+                queue!(screen, MoveTo(area.x, y), PrintStyledContent(self.theme.style(self.theme.gutter).apply(line.as_str())))?;
+            }
+        }
+
+        if self.command_line.prefix.is_some()
+        {
+            self.command_line.draw(&mut screen, cols, rows, self.theme.text)?;
+        }
+        else
+        {
+            self.draw_status(&mut screen, cols, rows)?;
+            self.buffer().draw_cursor(&mut screen)?;
+        }
+
+        if self.options.modal
+        {
+            let style = if self.command_line.prefix.is_some() { TMode::Insert } else { self.mode };
+            queue!(screen, style.cursor_style())?;
+        }
+
+        screen.flush()
+    }
+
+    fn mode(&self) -> &'static str
+    {
+        if self.options.modal
+        {
+            self.mode.name()
+        }
+        else if self.buffers[self.windows[self.current].buffer].read_only
+        {
+            "VIEW"
+        }
+        else
+        {
+            "EDIT"
+        }
+    }
+
+    fn draw_status(&self, screen: &mut impl Write, cols: u16, rows: u16) -> std::io::Result<()>
+    {
+        let t_file = &self.buffers[self.windows[self.current].buffer];
+
+        let modified = if t_file.modified { " [+]" } else { "" };
+        let read_only = if t_file.read_only { " [RO]" } else { "" };
+        let mut left = format!(" {}{modified}{read_only}", t_file.name());
+        if let Some((register, _)) = &self.recording
+        {
+            left = format!("{left}  recording @{register}");
+        }
+        if !self.message.is_empty()
+        {
+            left = format!("{left}  {}", self.message);
+        }
+
+        let (line, column) = t_file.position();
+        let total = match &t_file.large
+        {
+            Some(large) if !large.is_indexed() => format!("~{}", t_file.line_count()),
+            _ => t_file.line_count().to_string(),
+        };
+        let language = t_file.syntax.as_ref().map_or(String::new(), |s| format!("{} │ ", s.language.name));
+        let right = format!(
+            "{} │ {language}{} │ {} │ {} │ {line}:{column} │ {total} lines │ {}% ",
+            self.mode(),
+            describe_indent(&t_file.indent_unit(&self.options)),
+            t_file.encoding(),
+            t_file.line_ending(),
+            t_file.percentage(),
+        );
+
+        let space = (cols as usize).saturating_sub(right.chars().count());
+        let left: String = left.chars().take(space).collect();
+        let status: String = format!("{left:<space$}{right}").chars().take(cols as usize).collect();
+
+        queue!(screen, MoveTo(0, rows - 1), PrintStyledContent(self.theme.style(self.theme.status).apply(status)))
+    }
+
+    fn tabs(&self, cols: u16) -> Vec<(usize, u16, String)>
+    {
+        let active = self.windows[self.current].buffer;
+        let labels: Vec<String> = self.buffers.iter().map(|t_file| {
+            let name = Path::new(t_file.name()).file_name().map_or(t_file.name().into(), |n| n.to_string_lossy());
+            let modified = if t_file.modified { " +" } else { "" };
+            format!(" {name}{modified} ")
+        }).collect();
+
+        let mut first = 0;
+        while first < active && labels[first..=active].iter().map(|l| l.chars().count() + 1).sum::<usize>() > cols as usize
+        {
+            first += 1;
+        }
+
+        let mut tabs = Vec::new();
+        let mut x: usize = 0;
+        for (i, label) in labels.into_iter().enumerate().skip(first)
+        {
+            if x >= cols as usize
+            {
+                break;
+            }
+            let width = label.chars().count() + 1;
+            tabs.push((i, x as u16, label));
+            x += width;
+        }
+
+        tabs
+    }
+
+    fn draw_tabs(&self, screen: &mut impl Write, cols: u16) -> std::io::Result<()>
+    {
+        let active = self.windows[self.current].buffer;
+
+        queue!(screen, MoveTo(0, 0), PrintStyledContent(self.theme.text.apply(" ".repeat(cols as usize))))?;
+        for (i, x, label) in self.tabs(cols)
+        {
+            let label: String = label.chars().take((cols - x) as usize).collect();
+            let label_width = label.chars().count();
+            let style = if i == active { self.theme.status } else { self.theme.tab };
+            queue!(screen, MoveTo(x, 0), PrintStyledContent(self.theme.style(style).apply(label)))?;
+            if (x as usize + label_width) < cols as usize
+            {
+                queue!(screen, PrintStyledContent(self.theme.style(self.theme.gutter).apply("│")))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn click_tab(&mut self, column: u16)
+    {
+        let (cols, _) = size().unwrap();
+        let tab = self.tabs(cols).into_iter().rev().find(|(_, x, _)| *x <= column);
+
+        if let Some((i, _, _)) = tab
+        {
+            self.switch(i);
+        }
+    }
+
+    fn open(&mut self)
+    {
+        execute!(screen(), DisableLineWrap).unwrap();
+        let picked = TFileExplorer::new(&self.options.explorer, self.theme.clone()).pick(&self.keymap);
+        execute!(screen(), EnableLineWrap).unwrap();
+
+        if let Some(path) = picked
+        {
+            self.open_path(path.as_str());
+        }
+    }
+
+    fn open_path(&mut self, path: &str)
+    {
+        let canonical = Path::new(path).canonicalize().ok();
+        let open = self.buffers.iter().position(|t_file| {
+            !t_file.path.is_empty() && Path::new(t_file.path.as_str()).canonicalize().ok() == canonical
+        });
+
+        match open
+        {
+            Some(i) => self.switch(i),
+            None => match open_file(path, self.options.view)
+            {
+                Ok(t_file) => {
+                    self.buffers.push(t_file);
+                    self.configure(self.buffers.len() - 1);
+                    self.switch(self.buffers.len() - 1);
+                },
+                Err(err) => { self.message = err.to_string(); },
+            },
+        }
+    }
+
+    fn pick_buffer(&self) -> Option<usize>
+    {
+        let mut selected = self.windows[self.current].buffer;
+
+        loop
+        {
+            self.draw_buffers(selected).unwrap();
+
+            if let Event::Key(event) = read().unwrap()
+            {
+                if event.kind == KeyEventKind::Press
+                {
+                    match event.code
+                    {
+                        KeyCode::Esc | KeyCode::End | KeyCode::Delete => return None,
+                        KeyCode::Up => { selected = selected.saturating_sub(1); },
+                        KeyCode::Down => { selected = (selected + 1).min(self.buffers.len() - 1); },
+                        KeyCode::Enter | KeyCode::Char(' ') => return Some(selected),
+                        _ => {},
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_buffers(&self, selected: usize) -> std::io::Result<()>
+    {
+        let (_, rows) = size()?;
+        let shown = rows as usize - 1;
+        let first = (selected + 1).saturating_sub(shown);
+
+        let mut screen = screen();
+
+        queue!(
+            screen,
+            SetStyle(self.theme.text),
+            Clear(ClearType::All),
+            Clear(ClearType::Purge),
+            MoveTo(0, 0),
+            Print("Buffers"),
+        )?;
+
+        for (row, (i, t_file)) in self.buffers.iter().enumerate().skip(first).take(shown).enumerate()
+        {
+            let branch = if i == self.buffers.len() - 1 { "└──" } else { "├──" };
+            let modified = if t_file.modified { " [+]" } else { "" };
+            queue!(
+                screen,
+                MoveTo(0, row as u16 + 1),
+                Print(format!("{branch}{}{modified}", t_file.name())),
+            )?;
+        }
+
+        queue!(screen, MoveTo(3, (selected - first) as u16 + 1))?;
+
+        screen.flush()
+    }
+
+    fn prompt(&self, message: &str) -> KeyCode
+    {
+        let (_, rows) = size().unwrap();
+        execute!(
+            screen(),
+            MoveTo(0, rows - 1),
+            Clear(ClearType::CurrentLine),
+            PrintStyledContent(self.theme.style(self.theme.status).apply(message)),
+        ).unwrap();
+
+        loop
+        {
+            if let Event::Key(event) = read().unwrap()
+            {
+                if event.kind == KeyEventKind::Press
+                {
+                    return event.code;
+                }
+            }
+        }
+    }
+
+    fn autosave(&mut self)
+    {
+        for t_file in self.buffers.iter_mut().filter(|t_file| t_file.modified && !t_file.path.is_empty())
+        {
+            if t_file.save().is_err()
+            {
+                self.message = format!("Cannot autosave {}.", t_file.name());
+            }
+        }
+    }
+
+    fn save_all(&mut self) -> bool
+    {
+        for i in 0..self.buffers.len()
+        {
+            if self.buffers[i].save().is_err()
+            {
+                self.switch(i);
+                self.message = format!("Cannot save {}.", self.buffers[i].name());
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn quit(&mut self) -> bool
+    {
+        for i in 0..self.buffers.len()
+        {
+            if !self.buffers[i].is_unsaved()
+            {
+                continue;
+            }
+
+            self.switch(i);
+            self.draw().unwrap();
+            let question = format!("Save changes to {}? (y/n/c)", self.buffers[i].name());
+            loop
+            {
+                match self.prompt(question.as_str())
+                {
+                    KeyCode::Char('y') => {
+                        if self.buffers[i].save().is_err()
+                        {
+                            self.message = format!("Cannot save {}.", self.buffers[i].name());
+                            return false;
+                        }
+                        break;
+                    },
+                    KeyCode::Char('n') => break,
+                    KeyCode::Char('c') | KeyCode::Esc => return false,
+                    _ => {},
+                }
+            }
+        }
+
+        true
+    }
+}
+
+
+fn describe_indent(unit: &str) -> String
+{
+    if unit == "\t" { String::from("tabs") } else { format!("{} spaces", unit.len()) }
+}
+
+
+fn macro_path(name: &str) -> Result<PathBuf, &'static str>
+{
+    if name.contains(['/', '\\']) || name.starts_with('.')
+    {
+        return Err("Invalid macro name.");
+    }
+
+    Ok(macros_dir().ok_or("No data directory.")?.join(name))
+}
+
+
+fn open_file(file_path: &str, view: bool) -> Result<TFile, &'static str>
+{
+    if !view
+    {
+        match File::options().write(true).read(true).create(true).truncate(false).open(file_path)
+        {
+            Ok(f) => return TFile::build(f, file_path, false),
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {},
+            Err(_) => return Err("File cannot be opened"),
+        }
+    }
+
+    match File::open(file_path)
+    {
+        Ok(f) => TFile::build(f, file_path, true),
+        Err(err) => {
+            match err.kind()
+            {
+                ErrorKind::NotFound => Err("File doesn't exist."),
+                _ => Err("File cannot be opened"),
+            }
+        },
+    }
+}
@@ -0,0 +1,358 @@
+use std::fs;
+use std::path::{absolute, Path};
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TIndentStyle
+{
+    Tab,
+    Space,
+}
+
+
+#[derive(Default, Clone)]
+pub struct TEditorConfig
+{
+    pub indent_style: Option<TIndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub crlf: Option<bool>,
+    pub bom: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    pub max_line_length: Option<usize>,
+}
+
+
+impl TEditorConfig
+{
+    pub fn load(path: &str) -> TEditorConfig
+    {
+        let mut t_editorconfig = TEditorConfig::default();
+        let Ok(path) = absolute(path) else { return t_editorconfig };
+
+        let mut files = Vec::new();
+        for dir in path.ancestors().skip(1)
+        {
+            if let Ok(content) = fs::read_to_string(dir.join(".editorconfig"))
+            {
+                let root = is_root(&content);
+                files.push((dir, content));
+                if root
+                {
+                    break;
+                }
+            }
+        }
+
+        for (dir, content) in files.iter().rev()
+        {
+            t_editorconfig.read(dir, &path, content);
+        }
+
+        t_editorconfig
+    }
+
+    fn read(&mut self, dir: &Path, path: &Path, content: &str)
+    {
+        let Ok(relative) = path.strip_prefix(dir) else { return };
+        let relative: Vec<char> = relative.to_string_lossy().replace('\\', "/").chars().collect();
+
+        let mut matched = false;
+        for line in content.lines().map(str::trim)
+        {
+            if line.is_empty() || line.starts_with(['#', ';'])
+            {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']'))
+            {
+                let pattern = match section.strip_prefix('/')
+                {
+                    Some(pattern) => pattern.to_string(),
+                    None if section.contains('/') => section.to_string(),
+                    None => format!("**/{section}"),
+                };
+                let pattern: Vec<char> = pattern.chars().collect();
+                matched = glob(&pattern, &relative) || (!section.contains('/') && glob(&pattern[3..], &relative));
+            }
+            else if let (true, Some((key, value))) = (matched, line.split_once('='))
+            {
+                self.set(key.trim().to_lowercase().as_str(), value.trim().to_lowercase().as_str());
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str)
+    {
+        let flag = match value
+        {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        let number = value.parse().ok().filter(|n| (1..=16).contains(n));
+
+        match key
+        {
+            "indent_style" => {
+                self.indent_style = match value
+                {
+                    "tab" => Some(TIndentStyle::Tab),
+                    "space" => Some(TIndentStyle::Space),
+                    _ => None,
+                };
+            },
+            "indent_size" => { self.indent_size = number; },
+            "tab_width" => { self.tab_width = number; },
+            "end_of_line" => {
+                self.crlf = match value
+                {
+                    "lf" => Some(false),
+                    "crlf" => Some(true),
+                    _ => None,
+                };
+            },
+            "charset" => {
+                self.bom = match value
+                {
+                    "utf-8" => Some(false),
+                    "utf-8-bom" => Some(true),
+                    _ => None,
+                };
+            },
+            "trim_trailing_whitespace" => { self.trim_trailing_whitespace = flag; },
+            "insert_final_newline" => { self.insert_final_newline = flag; },
+            "max_line_length" => { self.max_line_length = value.parse().ok().filter(|n| *n > 0); },
+            _ => {},
+        }
+    }
+
+    pub fn tab_width(&self) -> Option<usize>
+    {
+        self.tab_width.or(self.indent_size)
+    }
+
+    pub fn indent(&self, tab_width: usize) -> Option<String>
+    {
+        match self.indent_style?
+        {
+            TIndentStyle::Tab => Some(String::from("\t")),
+            TIndentStyle::Space => Some(" ".repeat(self.indent_size.or(self.tab_width).unwrap_or(tab_width))),
+        }
+    }
+}
+
+
+fn is_root(content: &str) -> bool
+{
+    content.lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .any(|(key, value)| key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true"))
+}
+
+
+fn glob(pattern: &[char], path: &[char]) -> bool
+{
+    match pattern.first()
+    {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => (0..=path.len()).any(|i| glob(&pattern[2..], &path[i..])),
+        Some('*') => (0..=path.len())
+            .take_while(|i| *i == 0 || path[i - 1] != '/')
+            .any(|i| glob(&pattern[1..], &path[i..])),
+        Some('?') => path.first().is_some_and(|c| *c != '/') && glob(&pattern[1..], &path[1..]),
+        Some('[') => match pattern.iter().skip(2).position(|c| *c == ']').map(|i| i + 2)
+        {
+            Some(end) => {
+                let (negated, class) = match pattern[1]
+                {
+                    '!' | '^' => (true, &pattern[2..end]),
+                    _ => (false, &pattern[1..end]),
+                };
+                path.first().is_some_and(|c| *c != '/' && in_class(class, *c) != negated) && glob(&pattern[end + 1..], &path[1..])
+            },
+            None => path.first() == Some(&'[') && glob(&pattern[1..], &path[1..]),
+        },
+        Some('{') => match closing_brace(pattern)
+        {
+            Some(end) => braces(&pattern[1..end], &pattern[end + 1..], path),
+            None => path.first() == Some(&'{') && glob(&pattern[1..], &path[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => path.first() == Some(&pattern[1]) && glob(&pattern[2..], &path[1..]),
+        Some(c) => path.first() == Some(c) && glob(&pattern[1..], &path[1..]),
+    }
+}
+
+
+fn in_class(class: &[char], c: char) -> bool
+{
+    let mut i = 0;
+    while i < class.len()
+    {
+        if class.get(i + 1) == Some(&'-') && i + 2 < class.len()
+        {
+            if (class[i]..=class[i + 2]).contains(&c)
+            {
+                return true;
+            }
+            i += 3;
+        }
+        else
+        {
+            if class[i] == c
+            {
+                return true;
+            }
+            i += 1;
+        }
+    }
+
+    false
+}
+
+
+fn closing_brace(pattern: &[char]) -> Option<usize>
+{
+    let mut depth = 0;
+    for (i, c) in pattern.iter().enumerate()
+    {
+        match c
+        {
+            '{' => { depth += 1; },
+            '}' => {
+                depth -= 1;
+                if depth == 0
+                {
+                    return Some(i);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    None
+}
+
+
+fn braces(inner: &[char], rest: &[char], path: &[char]) -> bool
+{
+    let text: String = inner.iter().collect();
+    if let Some((Ok(low), Ok(high))) = text.split_once("..").map(|(a, b)| (a.parse::<i64>(), b.parse::<i64>()))
+    {
+        let digits = path.iter()
+            .enumerate()
+            .take_while(|(i, c)| c.is_ascii_digit() || (*i == 0 && **c == '-'))
+            .count();
+        return (1..=digits).any(|n| {
+            path[..n].iter().collect::<String>().parse().is_ok_and(|v: i64| (low.min(high)..=low.max(high)).contains(&v))
+                && glob(rest, &path[n..])
+        });
+    }
+
+    let mut alternatives = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.iter().enumerate()
+    {
+        match c
+        {
+            '{' => { depth += 1; },
+            '}' => { depth -= 1; },
+            ',' if depth == 0 => {
+                alternatives.push(&inner[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    alternatives.push(&inner[start..]);
+
+    if alternatives.len() == 1
+    {
+        let literal: Vec<char> = std::iter::once('{').chain(inner.iter().copied()).chain(std::iter::once('}')).collect();
+        return path.starts_with(&literal) && glob(rest, &path[literal.len()..]);
+    }
+
+    alternatives.iter().any(|alternative| glob(&[alternative, rest].concat(), path))
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool
+    {
+        glob(&pattern.chars().collect::<Vec<char>>(), &path.chars().collect::<Vec<char>>())
+    }
+
+    #[test]
+    fn matches_stars()
+    {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("**.rs", "src/main.rs"));
+        assert!(matches("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(!matches("*.rs", "main.rsx"));
+    }
+
+    #[test]
+    fn matches_question_marks_and_classes()
+    {
+        assert!(matches("?.md", "a.md"));
+        assert!(!matches("?.md", "/.md"));
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(!matches("[!abc].txt", "b.txt"));
+        assert!(matches("[a-c][0-9]", "c7"));
+        assert!(!matches("[a-c]", "d"));
+        assert!(matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn matches_braces()
+    {
+        assert!(matches("*.{js,ts}", "app.ts"));
+        assert!(!matches("*.{js,ts}", "app.rs"));
+        assert!(matches("{a,{b,c}}.txt", "c.txt"));
+        assert!(matches("file{1..10}", "file7"));
+        assert!(!matches("file{1..10}", "file11"));
+        assert!(matches("n{-3..3}", "n-2"));
+        assert!(matches("{single}", "{single}"));
+        assert!(matches("{open", "{open"));
+    }
+
+    #[test]
+    fn matches_escapes()
+    {
+        assert!(matches("\\*.txt", "*.txt"));
+        assert!(!matches("\\*.txt", "a.txt"));
+    }
+
+    #[test]
+    fn reads_sections_in_order()
+    {
+        let content = "root = true\n[*]\nindent_style = space\nindent_size = 2\n[*.go]\nindent_style = tab\n[/docs/*.md]\nmax_line_length = 80\n";
+        let dir = Path::new("/project");
+
+        let mut go = TEditorConfig::default();
+        go.read(dir, Path::new("/project/cmd/main.go"), content);
+        assert!(go.indent_style == Some(TIndentStyle::Tab));
+        assert_eq!(go.indent(4).as_deref(), Some("\t"));
+
+        let mut md = TEditorConfig::default();
+        md.read(dir, Path::new("/project/docs/a.md"), content);
+        assert_eq!(md.indent(4).as_deref(), Some("  "));
+        assert_eq!(md.max_line_length, Some(80));
+
+        let mut nested = TEditorConfig::default();
+        nested.read(dir, Path::new("/project/src/docs/a.md"), content);
+        assert_eq!(nested.max_line_length, None);
+
+        assert!(is_root(content));
+        assert!(!is_root("[*]\nroot = true\n"));
+    }
+}
@@ -8,24 +8,26 @@ pub trait TEvent
 }
 
 
-pub struct InsertEvent(pub String);
+pub struct InsertEvent(pub String, pub usize);
 
 
 impl TEvent for InsertEvent
 {
     fn invoke(&mut self, t_file: &mut TFile)
     {
+        self.1 = t_file.index;
         t_file.insert(self.0.as_str()).unwrap();
     }
 
     fn reverse(&self, t_file: &mut TFile)
     {
-        t_file.delete(self.0.len()).unwrap();
+        t_file.move_to(self.1 + self.0.len()).unwrap();
+        t_file.delete(self.0.chars().count()).unwrap();
     }
 }
 
 
-pub struct DeleteEvent(pub usize, pub String);
+pub struct DeleteEvent(pub usize, pub String, pub usize);
 
 
 impl TEvent for DeleteEvent
@@ -33,10 +35,12 @@ impl TEvent for DeleteEvent
     fn invoke(&mut self, t_file: &mut TFile)
     {
         self.1 = t_file.delete(self.0).unwrap();
+        self.2 = t_file.index;
     }
 
     fn reverse(&self, t_file: &mut TFile)
     {
+        t_file.move_to(self.2).unwrap();
         t_file.insert(self.1.as_str()).unwrap();
     }
 }
@@ -48,6 +52,8 @@ pub enum Direction
     Down,
     Left,
     Right,
+    PageUp,
+    PageDown,
 }
 
 pub struct MoveEvent(pub Direction, pub usize);
@@ -64,6 +70,8 @@ impl TEvent for MoveEvent
             Direction::Down => { t_file.move_down().unwrap() },
             Direction::Left => { t_file.move_left(1).unwrap() },
             Direction::Right => { t_file.move_right(1).unwrap() },
+            Direction::PageUp => { t_file.page_up().unwrap() },
+            Direction::PageDown => { t_file.page_down().unwrap() },
         };
     }
 
@@ -71,4 +79,43 @@ impl TEvent for MoveEvent
     {
         t_file.move_to(self.1).unwrap();
     }
+}
+
+pub struct ReplaceEvent(pub [usize; 2], pub String, pub String);
+
+
+impl TEvent for ReplaceEvent
+{
+    fn invoke(&mut self, t_file: &mut TFile)
+    {
+        self.2 = t_file.replace(self.0, self.1.as_str()).unwrap();
+    }
+
+    fn reverse(&self, t_file: &mut TFile)
+    {
+        t_file.replace([self.0[0], self.0[0] + self.1.len()], self.2.as_str()).unwrap();
+    }
+}
+
+
+pub struct GroupEvent(pub Vec<Box<dyn TEvent>>);
+
+
+impl TEvent for GroupEvent
+{
+    fn invoke(&mut self, t_file: &mut TFile)
+    {
+        for t_event in self.0.iter_mut()
+        {
+            t_event.invoke(t_file);
+        }
+    }
+
+    fn reverse(&self, t_file: &mut TFile)
+    {
+        for t_event in self.0.iter().rev()
+        {
+            t_event.reverse(t_file);
+        }
+    }
 }
\ No newline at end of file
@@ -1,349 +1,1696 @@
 use crossterm::{
-    cursor::{MoveTo, position},
-    terminal::{size, Clear, ClearType},
-    ExecutableCommand, execute,
+    cursor::MoveTo,
+    terminal::size,
+    style::{ContentStyle, PrintStyledContent},
+    queue,
 };
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{stdout, Read};
+use std::io::{stdin, Read, Seek, Write};
+use std::ops::Range;
 
-use crate::t_event::{TEvent};
+use crate::t_editorconfig::TEditorConfig;
+use crate::t_event::{GroupEvent, ReplaceEvent, TEvent};
+use crate::t_window::TRect;
+use crate::t_large_file::{TLargeFile, LARGE_FILE_SIZE, WINDOW_BYTES, WINDOW_LINES};
+use crate::t_options::{TNumbers, TOptions};
+use crate::t_marks;
+use crate::t_syntax::{TBrackets, TScope, TSyntax};
+use crate::t_theme::TTheme;
+
+
+pub struct TLine
+{
+    pub text: String,
+    pub number: usize,
+    pub wrapped: bool,
+    pub indent: usize,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum THighlight
+{
+    Selection,
+    Search,
+    Match,
+    Mismatch,
+    Syntax(TScope),
+}
+
+
+impl THighlight
+{
+    fn style(&self, theme: &TTheme) -> ContentStyle
+    {
+        match self
+        {
+            THighlight::Selection => theme.selection,
+            THighlight::Search => theme.search,
+            THighlight::Match => theme.matching,
+            THighlight::Mismatch => theme.mismatch,
+            THighlight::Syntax(scope) => theme.syntax[*scope as usize],
+        }
+    }
+}
 
 
 pub struct TFile
 {
-    pub file: File,
+    pub file: Option<File>,
+    pub path: String,
     pub content: String,
     pub index: usize,
     pub view: [usize; 2],
-    pub lines: Vec<String>,
+    pub offset: usize,
+    pub area: TRect,
+    pub lines: Vec<TLine>,
+    pub lines_start: usize,
+    pub lines_end: usize,
+    pub numbers: TNumbers,
+    pub gutter: u16,
+    pub tab_width: usize,
+    pub wrap: bool,
+    pub word_wrap: bool,
+    pub wrap_indent: bool,
+    pub wrap_marker: String,
+    pub visual_rows: bool,
+    pub detected_indent: Option<String>,
+    pub indent_override: Option<String>,
+    pub editorconfig: TEditorConfig,
+    pub highlight: String,
+    pub syntax: Option<TSyntax>,
+    pub colors: bool,
+    pub brackets: TBrackets,
+    pub anchor: Option<usize>,
+    pub expansions: Vec<[usize; 2]>,
+    pub marks: BTreeMap<char, usize>,
     pub event_buffer: Vec<Box<dyn TEvent>>,
+    pub undo_limit: usize,
+    pub group: Option<Vec<Box<dyn TEvent>>>,
+    pub group_depth: usize,
+    pub large: Option<TLargeFile>,
+    pub read_only: bool,
+    pub modified: bool,
+    pub crlf: bool,
+    pub bom: bool,
 }
 
 
 impl TFile
 {
-    pub fn build(file: File) -> Result<TFile, &'static str>
+    fn new(file: Option<File>, path: &str, content: String, read_only: bool) -> TFile
     {
-        let (_, rows) = size().unwrap();
-        let mut t_file = TFile { file,
-            content: String::new(),
+        let (cols, rows) = size().unwrap();
+        let syntax = TSyntax::detect(path, &content);
+        TFile { file,
+            path: path.to_string(),
+            content,
             index: 0,
             view: [0, rows as usize],
+            offset: 0,
+            area: TRect { x: 0, y: 0, width: cols, height: rows },
             lines: Vec::new(),
-            event_buffer: Vec::new()
+            lines_start: 0,
+            lines_end: 0,
+            numbers: TNumbers::Off,
+            gutter: 0,
+            tab_width: 4,
+            wrap: true,
+            word_wrap: true,
+            wrap_indent: false,
+            wrap_marker: String::from("↪ "),
+            visual_rows: true,
+            detected_indent: None,
+            indent_override: None,
+            editorconfig: TEditorConfig::default(),
+            highlight: String::new(),
+            syntax,
+            colors: true,
+            brackets: TBrackets::default(),
+            anchor: None,
+            expansions: Vec::new(),
+            marks: BTreeMap::new(),
+            event_buffer: Vec::new(),
+            undo_limit: 10,
+            group: None,
+            group_depth: 0,
+            large: None,
+            read_only,
+            modified: false,
+            crlf: false,
+            bom: false,
+        }
+    }
+
+    fn normalize(mut self) -> TFile
+    {
+        if let Some(content) = self.content.strip_prefix('\u{feff}')
+        {
+            self.content = content.to_string();
+            self.bom = true;
+        }
+
+        if self.content.contains("\r\n")
+        {
+            self.content = self.content.replace("\r\n", "\n");
+            self.crlf = true;
+        }
+
+        self.detected_indent = detect_indent(&self.content);
+
+        self
+    }
+
+    pub fn build(mut file: File, path: &str, read_only: bool) -> Result<TFile, &'static str>
+    {
+        let len = match file.metadata()
+        {
+            Ok(m) => m.len(),
+            Err(_) => return Err("Problem reading file."),
         };
 
-        match t_file.file.read_to_string(&mut t_file.content)
+        if len > LARGE_FILE_SIZE
+        {
+            return match TLargeFile::build(&mut file, path)
+            {
+                Ok((large, content)) => {
+                    let mut t_file = TFile::new(Some(file), path, content, true);
+                    t_file.large = Some(large);
+                    t_file.syntax = None;
+                    Ok(t_file)
+                },
+                Err(_) => Err("Problem reading file."),
+            };
+        }
+
+        let mut content = String::new();
+        match file.read_to_string(&mut content)
         {
-            Ok(_) => Ok(t_file),
+            Ok(_) => {
+                let mut t_file = TFile::new(Some(file), path, content, read_only).normalize();
+                t_file.configure();
+                t_file.load_marks();
+                Ok(t_file)
+            },
             Err(_) => Err("Problem reading file."),
         }
     }
 
-    pub fn add_event(&mut self, mut t_event: impl TEvent + 'static)
+    fn configure(&mut self)
     {
-        t_event.invoke(self);
-        self.event_buffer.push(Box::new(t_event));
+        self.editorconfig = TEditorConfig::load(&self.path);
+        self.crlf = self.editorconfig.crlf.unwrap_or(self.crlf);
+        self.bom = self.editorconfig.bom.unwrap_or(self.bom);
+    }
 
-        if self.event_buffer.len() > 10
+    pub fn from_stdin(read_only: bool) -> Result<TFile, &'static str>
+    {
+        let mut content = String::new();
+        match stdin().read_to_string(&mut content)
         {
-            self.event_buffer.remove(0);
+            Ok(_) => Ok(TFile::new(None, "", content, read_only).normalize()),
+            Err(_) => Err("Problem reading stdin."),
         }
     }
 
-    pub fn undo(&mut self)
+    pub fn name(&self) -> &str
     {
-        if self.event_buffer.len() > 0
+        if self.path.is_empty()
+        {
+            "[stdin]"
+        }
+        else
         {
-            self.event_buffer.pop().unwrap().reverse(self);
+            self.path.as_str()
         }
     }
 
-    pub fn insert(&mut self, string: &str) -> std::io::Result<()>
+    pub fn encoding(&self) -> &'static str
     {
-        self.content.insert_str(self.index, string);
-        self.index += string.len();
+        if self.bom
+        {
+            "UTF-8 BOM"
+        }
+        else
+        {
+            "UTF-8"
+        }
+    }
+
+    pub fn line_ending(&self) -> &'static str
+    {
+        if self.crlf
+        {
+            "CRLF"
+        }
+        else
+        {
+            "LF"
+        }
+    }
 
-        self.clear_screen()?;
+    pub fn position(&self) -> (usize, usize)
+    {
+        let before = &self.content[..self.index];
+        let line = self.first_line() + before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
 
-        Ok(())
+        (line, column)
     }
 
-    pub fn delete(&mut self, units: usize) -> std::io::Result<String>
+    pub fn line_count(&self) -> usize
     {
-        let mut string = String::new();
-        for _ in 0..units
+        match &self.large
         {
-            if self.content.len() > 0
-            {
-                if self.index > 0
-                {
-                    string.push(self.content.remove(self.index - 1));
-                    self.index -= 1;
-                }
-                else
-                {
-                    string.push(self.content.remove(self.index));
-                }
-                self.clear_screen()?;
-            }
+            Some(large) => large.line_count(),
+            None => self.content.lines().count().max(1),
         }
+    }
 
-        Ok(string)
+    pub fn percentage(&self) -> usize
+    {
+        match &self.large
+        {
+            Some(large) => ((large.start + self.index as u64) * 100 / large.len.max(1)) as usize,
+            None => (self.position().0 * 100 / self.line_count()).min(100),
+        }
     }
 
-    pub fn move_up(&mut self) -> std::io::Result<()>
+    pub fn to_bytes(&self) -> Vec<u8>
     {
-        let mut line_index: usize = 0;
-        for (i, line) in self.lines.iter().enumerate()
+        let mut bytes = Vec::new();
+        if self.bom
         {
-            if line_index + line.len() > self.index
-            {
-                if i == 0
-                {
-                    self.index = 0;
-                    break;
-                }
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+        }
 
-                let index = self.index - line_index;
+        if self.crlf
+        {
+            bytes.extend_from_slice(self.content.replace('\n', "\r\n").as_bytes());
+        }
+        else
+        {
+            bytes.extend_from_slice(self.content.as_bytes());
+        }
 
-                self.index -= line[..index + 1].len();
+        bytes
+    }
 
-                if self.lines.get(i - 1).unwrap().len() > index
-                {
-                    self.index -= self.lines.get(i - 1).unwrap().len() - index - 1;
-                }
+    pub fn is_unsaved(&self) -> bool
+    {
+        self.modified && !self.read_only && self.file.is_some()
+    }
 
-                break;
-            }
-            else if line_index + line.len() == self.index && i == self.lines.len() - 1
-            {
-                self.index -= self.lines.get(i).unwrap().len();
-            }
-            line_index += line.len();
+    pub fn save(&mut self) -> std::io::Result<()>
+    {
+        if self.read_only || !self.modified
+        {
+            return Ok(());
+        }
+
+        self.write()
+    }
+
+    pub fn write(&mut self) -> std::io::Result<()>
+    {
+        self.tidy()?;
+        let bytes = self.to_bytes();
+        if let Some(file) = self.file.as_mut()
+        {
+            file.set_len(0)?;
+            file.rewind()?;
+            file.write_all(&bytes)?;
+            self.modified = false;
         }
 
-        self.view_changed()?;
+        self.store_marks();
 
         Ok(())
     }
 
-    pub fn move_down(&mut self) -> std::io::Result<()>
+    pub fn write_to(&mut self, path: &str) -> std::io::Result<()>
     {
-        let mut line_index: usize = 0;
-        for (i, line) in self.lines.iter().enumerate()
-        {
-            if i == self.lines.len() - 1
-            {
-                self.index = self.content.len();
-                break;
-            }
+        let file = File::options().write(true).read(true).create(true).truncate(false).open(path)?;
+        self.file = Some(file);
+        self.path = path.to_string();
+        self.syntax = TSyntax::detect(path, &self.content);
+        self.brackets = TBrackets::default();
+        self.configure();
 
-            if line_index + line.len() > self.index
-            {
-                let index = self.index - line_index;
+        self.write()
+    }
 
-                self.index += line[index..].len();
+    fn tidy(&mut self) -> std::io::Result<()>
+    {
+        if self.large.is_some()
+        {
+            return Ok(());
+        }
 
-                if index >= self.lines.get(i + 1).unwrap().len()
-                {
-                    self.index += self.lines.get(i + 1).unwrap().len() - 1;
-                }
-                else
+        let mut edits = Vec::new();
+        if self.editorconfig.trim_trailing_whitespace == Some(true)
+        {
+            let mut start = 0;
+            for line in self.content.split_inclusive('\n')
+            {
+                let text = line.trim_end_matches('\n');
+                let end = start + text.trim_end_matches([' ', '\t']).len();
+                if end < start + text.len()
                 {
-                    self.index += index;
+                    edits.push(([end, start + text.len()], ""));
                 }
+                start += line.len();
+            }
+        }
 
-                break;
+        let len = self.content.len();
+        match self.editorconfig.insert_final_newline
+        {
+            Some(true) if len > 0 && !self.content.ends_with('\n') => edits.push(([len, len], "\n")),
+            Some(false) if self.content.ends_with('\n') => edits.push(([self.content.trim_end_matches('\n').len(), len], "")),
+            _ => {},
+        }
+
+        if edits.is_empty()
+        {
+            return Ok(());
+        }
+
+        let mut index = self.index;
+        for ([start, end], string) in edits.iter().rev()
+        {
+            if index >= *end
+            {
+                index = index - (end - start) + string.len();
             }
+            else if index > *start
+            {
+                index = *start;
+            }
+        }
 
-            line_index += line.len();
+        self.begin_group();
+        for ([start, end], string) in edits.into_iter().rev()
+        {
+            self.add_event(ReplaceEvent([start, end], string.to_string(), String::new()));
         }
+        self.end_group();
 
-        self.view_changed()?;
+        self.index = floor_char_boundary(&self.content, index.min(self.content.len()));
+        self.relayout()
+    }
 
-        Ok(())
+    pub fn add_event(&mut self, mut t_event: impl TEvent + 'static)
+    {
+        t_event.invoke(self);
+
+        match self.group.as_mut()
+        {
+            Some(group) => group.push(Box::new(t_event)),
+            None => self.push_event(Box::new(t_event)),
+        }
     }
 
-    pub fn move_left(&mut self, units: usize) -> std::io::Result<()>
+    fn push_event(&mut self, t_event: Box<dyn TEvent>)
     {
-        if self.index > units - 1
+        self.event_buffer.push(t_event);
+
+        if self.event_buffer.len() > self.undo_limit
         {
-            self.index -= units;
-            self.view_changed()?;
+            self.event_buffer.remove(0);
         }
+    }
 
-        Ok(())
+    pub fn begin_group(&mut self)
+    {
+        self.group_depth += 1;
+        if self.group.is_none()
+        {
+            self.group = Some(Vec::new());
+        }
     }
 
-    pub fn move_right(&mut self, units: usize) -> std::io::Result<()>
+    pub fn end_group(&mut self)
     {
-        if self.index < self.content.len() + 1 - units
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth > 0
         {
-            self.index += units;
-            self.view_changed()?;
+            return;
         }
 
-        Ok(())
+        if let Some(group) = self.group.take().filter(|g| !g.is_empty())
+        {
+            self.push_event(Box::new(GroupEvent(group)));
+        }
     }
 
-    pub fn move_to(&mut self, index: usize) -> std::io::Result<()>
+    pub fn undo(&mut self)
     {
-        self.index = index.clamp(0, self.content.len());
+        self.group_depth = 0;
+        self.end_group();
 
-        self.view_changed()?;
-
-        Ok(())
+        if let Some(event) = self.event_buffer.pop()
+        {
+            event.reverse(self);
+        }
     }
 
-    pub fn clear_screen(&mut self) -> std::io::Result<()>
+    pub fn insert(&mut self, string: &str) -> std::io::Result<()>
     {
-        self.make_lines().unwrap();
-
-        self.make_view()?;
+        self.changed(self.index, self.index, string.len());
+        self.content.insert_str(self.index, string);
+        self.index += string.len();
+        self.modified = true;
 
-        execute!(
-            stdout(),
-            Clear(ClearType::All),
-            Clear(ClearType::Purge),
-            MoveTo(0, 0),
-        )?;
+        self.relayout()
+    }
 
-        let (_, rows) = size()?;
-        if self.lines.len() > rows as usize
+    pub fn delete(&mut self, units: usize) -> std::io::Result<String>
+    {
+        let mut string = String::new();
+        for _ in 0..units
         {
-            for (i, line) in self.lines[self.view[0]..self.view[1]].iter().enumerate()
+            if let Some(c) = self.content[..self.index].chars().next_back()
             {
-                if i < (rows - 1) as usize
-                {
-                    println!("{}", line.trim());
-                }
-                else
-                {
-                    print!("{}", line.trim());
-                }
+                self.index -= c.len_utf8();
+                self.changed(self.index, self.index + c.len_utf8(), 0);
+                string.insert(0, self.content.remove(self.index));
+                self.modified = true;
+            }
+            else if let Some(c) = self.content.chars().next()
+            {
+                self.changed(0, c.len_utf8(), 0);
+                string.push(self.content.remove(self.index));
+                self.modified = true;
             }
         }
-        else
-        {
-            print!("{}", self.content);
-
-            let mut print_string = String::new();
 
-            let (_, y) = position()?;
+        self.relayout()?;
 
-            for i in y + 1..rows
-            {
-                if i == 0
-                {
-                    print_string.push_str("~");
-                }
-                else
-                {
-                    print_string.push_str("\n~");
-                }
-            }
+        Ok(string)
+    }
 
-            print!("{}", print_string);
+    pub fn replace(&mut self, range: [usize; 2], string: &str) -> std::io::Result<String>
+    {
+        let removed = self.content[range[0]..range[1]].to_string();
+        self.changed(range[0], range[1], string.len());
+        self.content.replace_range(range[0]..range[1], string);
+        if self.index > range[0]
+        {
+            self.index = range[0];
         }
+        self.modified = true;
 
-        self.move_cursor()?;
+        self.relayout()?;
 
-        Ok(())
+        Ok(removed)
     }
 
-    fn move_cursor(&mut self) -> std::io::Result<()>
+    fn changed(&mut self, start: usize, end: usize, inserted: usize)
     {
-        let (cols, _) = size()?;
+        let line = self.content[..start].matches('\n').count();
+        if let Some(syntax) = self.syntax.as_mut()
+        {
+            syntax.invalidate(line);
+        }
+
+        self.brackets.invalidate(line);
+        self.shift_marks(start, end, inserted);
+    }
 
-        let mut cursor: [u16; 2] = [0, 0];
-        for (i, c) in self.content.chars().enumerate()
+    fn shift_marks(&mut self, start: usize, end: usize, inserted: usize)
+    {
+        for mark in self.marks.values_mut()
         {
-            if i < self.index
+            if *mark >= end
             {
-                if c == '\n' || cursor[0] >= cols - 1
-                {
-                    cursor = [0, cursor[1] + 1];
-                }
-                else
-                {
-                    cursor[0] += 1;
-                }
-            } else { break; }
+                *mark = *mark - (end - start) + inserted;
+            }
+            else if *mark > start
+            {
+                *mark = start;
+            }
         }
+    }
 
-        cursor[1] -= self.view[0] as u16;
+    pub fn indent_unit(&self, options: &TOptions) -> String
+    {
+        let detected = self.detected_indent.clone().filter(|_| options.detect_indent);
 
-        stdout().execute(MoveTo(cursor[0], cursor[1]))?;
+        self.indent_override.clone()
+            .or_else(|| self.editorconfig.indent(self.tab_width))
+            .or(detected)
+            .unwrap_or_else(|| options.indent())
+    }
 
-        Ok(())
+    pub fn line_indent(&self, index: usize) -> &str
+    {
+        let start = self.content[..index].rfind('\n').map_or(0, |i| i + 1);
+
+        leading_whitespace(&self.content[start..])
     }
 
-    fn view_changed(&mut self) -> std::io::Result<()>
+    pub fn newline(&self, unit: &str) -> String
     {
-        let v0 = self.view[0];
-        self.make_view()?;
-        if v0 != self.view[0]
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let indent = self.line_indent(self.index);
+        let indent = &indent[..indent.len().min(self.index - start)];
+
+        match &self.syntax
         {
-            self.clear_screen()?;
+            Some(syntax) if syntax.language.indents(&self.content[start..self.index]) => format!("\n{indent}{unit}"),
+            _ => format!("\n{indent}"),
         }
-        else
+    }
+
+    pub fn dedent(&self, c: char, unit: &str) -> Option<[usize; 2]>
+    {
+        if !self.syntax.as_ref()?.language.dedents(c)
         {
-            self.move_cursor()?;
+            return None;
         }
-        Ok(())
+
+        self.outdent(unit).map(|width| [self.index - width, self.index])
     }
 
-    fn make_view(&mut self) -> std::io::Result<()>
+    pub fn outdent(&self, unit: &str) -> Option<usize>
     {
-        let (_, rows) = size()?;
-        self.view[0] = 0;
-        self.view[1] = rows as usize;
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let before = &self.content[start..self.index];
+        if before.is_empty() || before.len() != self.line_indent(self.index).len()
+        {
+            return None;
+        }
 
-        let mut line_index: usize = 0;
-        for (i, line) in self.lines.iter().enumerate()
+        if before.ends_with('\t')
         {
-            if line_index + line.len() > self.index || i == self.lines.len() - 1
-            {
-                if i > rows as usize - 1
-                {
-                    self.view[0] += i - rows as usize + 1;
-                    self.view[1] += i - rows as usize + 1;
-                }
-                break;
-            }
-            line_index += line.len();
+            return Some(1);
         }
 
-        Ok(())
+        let size = if unit == "\t" { self.tab_width } else { unit.len() };
+        let spaces = before.len() - before.trim_end_matches(' ').len();
+        let column = display_width(before, self.tab_width) % size;
+
+        Some(spaces.min(if column == 0 { size } else { column }))
     }
 
-    fn make_lines(&mut self) -> Result<(), &'static str>
+    pub fn indent(&self, unit: &str) -> String
     {
-        let (cols, _) = match size() {
-            Ok(s) => s,
-            Err(_) => return Err("Function \"size()\" failed."),
-        };
+        if unit == "\t"
+        {
+            return unit.to_string();
+        }
 
-        self.lines.clear();
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let column = display_width(&self.content[start..self.index], self.tab_width);
 
-        for mut line in self.content.split("\n")
+        " ".repeat(unit.len() - column % unit.len())
+    }
+
+    pub fn set_mark(&mut self, name: char)
+    {
+        self.marks.insert(name, self.index);
+
+        if !self.modified
         {
-            loop
-            {
-                if line.len() <= cols as usize
-                {
-                    break;
-                }
+            self.store_marks();
+        }
+    }
 
-                let (s1, s2) = line.split_at(cols as usize);
-                self.lines.push(s1.to_string());
-                line = s2;
-            }
+    pub fn delete_marks(&mut self, names: &str)
+    {
+        self.marks.retain(|name, _| !names.contains(*name));
 
-            self.lines.push(format!("{line}\n"));
+        if !self.modified
+        {
+            self.store_marks();
         }
+    }
 
-        Ok(())
+    pub fn mark_position(&self, offset: usize) -> (usize, usize)
+    {
+        let before = &self.content[..offset.min(self.content.len())];
+        let line = before.matches('\n').count();
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count();
+
+        (line, column)
+    }
+
+    fn load_marks(&mut self)
+    {
+        for (name, (line, column)) in t_marks::load(self.path.as_str())
+        {
+            let start = match line
+            {
+                0 => Some(0),
+                _ => self.content.match_indices('\n').nth(line - 1).map(|(i, _)| i + 1),
+            };
+            if let Some(start) = start
+            {
+                let text = &self.content[start..];
+                let end = text.find('\n').unwrap_or(text.len());
+                self.marks.insert(name, start + column_index(&text[..end], column));
+            }
+        }
+    }
+
+    fn store_marks(&self)
+    {
+        if self.path.is_empty() || self.large.is_some()
+        {
+            return;
+        }
+
+        let marks = self.marks.iter().map(|(name, offset)| (*name, self.mark_position(*offset))).collect();
+
+        t_marks::store(self.path.as_str(), &marks).ok();
+    }
+
+    pub fn line_range(&self) -> [usize; 2]
+    {
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.content[self.index..].find('\n').map_or(self.content.len(), |i| self.index + i);
+
+        [start, end]
+    }
+
+    pub fn find(&self, pattern: &str, forward: bool) -> Option<usize>
+    {
+        if pattern.is_empty()
+        {
+            return None;
+        }
+
+        if forward
+        {
+            let from = (self.index + 1).min(self.content.len());
+            let from = floor_char_boundary(&self.content, from);
+            self.content[from..].find(pattern).map(|i| from + i).or_else(|| self.content.find(pattern))
+        }
+        else
+        {
+            self.content[..self.index].rfind(pattern).or_else(|| self.content.rfind(pattern))
+        }
+    }
+
+    pub fn goto_line(&mut self, line: usize) -> std::io::Result<()>
+    {
+        let mut line = line.max(1) - 1;
+
+        if let (Some(large), Some(file)) = (self.large.as_mut(), self.file.as_mut())
+        {
+            let loaded = self.content.matches('\n').count();
+            if line < large.first_line + large.partial as usize || line >= large.first_line + loaded
+            {
+                let (target, at) = large.seek_line(file, line)?;
+                line = target;
+                let before = line.saturating_sub(WINDOW_LINES / 2);
+                let (first_line, start) = match large.line_offset(before)
+                {
+                    Some(start) if at - start <= (WINDOW_BYTES / 2) as u64 => (before, start),
+                    _ => (line, at),
+                };
+                self.content = large.load(file, first_line, start)?;
+                self.brackets = TBrackets::default();
+                self.index = 0;
+                self.make_lines();
+            }
+        }
+
+        let line = line.saturating_sub(self.first_line());
+        let index = match line
+        {
+            0 => 0,
+            _ => self.content.match_indices('\n').nth(line - 1).map_or(self.content.len(), |(i, _)| i + 1),
+        };
+
+        self.move_to(index)
+    }
+
+    pub fn move_up(&mut self) -> std::io::Result<()>
+    {
+        if self.visual_rows { self.line_up() } else { self.logical_line_up() }
+
+        self.view_changed()
+    }
+
+    pub fn move_down(&mut self) -> std::io::Result<()>
+    {
+        if self.visual_rows { self.line_down() } else { self.logical_line_down() }
+
+        self.view_changed()
+    }
+
+    pub fn page_up(&mut self) -> std::io::Result<()>
+    {
+        for _ in 1..self.area.height
+        {
+            self.line_up();
+            self.shift()?;
+        }
+
+        self.view_changed()
+    }
+
+    pub fn page_down(&mut self) -> std::io::Result<()>
+    {
+        for _ in 1..self.area.height
+        {
+            self.line_down();
+            self.shift()?;
+        }
+
+        self.view_changed()
+    }
+
+    fn line_up(&mut self)
+    {
+        if self.lines_start > 0 && self.line_at(self.index).0 == 0
+        {
+            self.make_lines();
+        }
+
+        let (line, line_index) = self.line_at(self.index);
+        if line == 0
+        {
+            self.index = 0;
+            return;
+        }
+
+        let column = self.row_column(line, self.index - line_index);
+        let start = line_index - self.lines[line - 1].text.len();
+        self.index = start + self.row_index(line - 1, column);
+    }
+
+    fn line_down(&mut self)
+    {
+        if self.lines_end < self.content.len() && self.line_at(self.index).0 + 1 >= self.lines.len()
+        {
+            self.make_lines();
+        }
+
+        let (line, line_index) = self.line_at(self.index);
+        if line + 1 >= self.lines.len()
+        {
+            self.index = self.content.len();
+            return;
+        }
+
+        let column = self.row_column(line, self.index - line_index);
+        let start = line_index + self.lines[line].text.len();
+        self.index = (start + self.row_index(line + 1, column)).min(self.content.len());
+    }
+
+    fn logical_line_up(&mut self)
+    {
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        if start == 0
+        {
+            self.index = 0;
+            return;
+        }
+
+        let column = display_width(&self.content[start..self.index], self.tab_width);
+        let previous = self.content[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        self.index = previous + display_index(&self.content[previous..], column, self.tab_width);
+    }
+
+    fn logical_line_down(&mut self)
+    {
+        let Some(end) = self.content[self.index..].find('\n').map(|i| self.index + i) else {
+            self.index = self.content.len();
+            return;
+        };
+
+        let start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let column = display_width(&self.content[start..self.index], self.tab_width);
+        self.index = end + 1 + display_index(&self.content[end + 1..], column, self.tab_width);
+    }
+
+    fn row_column(&self, row: usize, offset: usize) -> usize
+    {
+        let line = &self.lines[row];
+        line.text[..offset].chars().fold(line.indent, |column, c| column + char_width(c, column, self.tab_width))
+    }
+
+    fn row_index(&self, row: usize, column: usize) -> usize
+    {
+        let line = &self.lines[row];
+        let mut width = line.indent;
+        for (i, c) in line.text.char_indices()
+        {
+            width += char_width(c, width, self.tab_width);
+            if c == '\n' || width > column
+            {
+                return i;
+            }
+        }
+
+        // the end of a wrapped row is the start of the next one
+        line.text.char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    pub fn move_left(&mut self, units: usize) -> std::io::Result<()>
+    {
+        for _ in 0..units
+        {
+            match self.content[..self.index].chars().next_back()
+            {
+                None => break,
+                Some(c) => self.index -= c.len_utf8(),
+            }
+        }
+
+        self.view_changed()
+    }
+
+    pub fn move_right(&mut self, units: usize) -> std::io::Result<()>
+    {
+        for _ in 0..units
+        {
+            match self.content[self.index..].chars().next()
+            {
+                None => break,
+                Some(c) => self.index += c.len_utf8(),
+            }
+        }
+
+        self.view_changed()
+    }
+
+    pub fn move_to(&mut self, index: usize) -> std::io::Result<()>
+    {
+        self.index = floor_char_boundary(&self.content, index);
+
+        self.view_changed()
+    }
+
+    pub fn restore(&mut self, index: usize, view: [usize; 2], offset: usize, area: TRect) -> std::io::Result<()>
+    {
+        self.index = floor_char_boundary(&self.content, index);
+        self.view = view;
+        self.offset = offset;
+        self.area = area;
+
+        self.relayout()
+    }
+
+    pub fn relayout(&mut self) -> std::io::Result<()>
+    {
+        self.make_lines();
+
+        self.make_view()
+    }
+
+    pub fn draw(&mut self, screen: &mut impl Write, theme: &TTheme) -> std::io::Result<()>
+    {
+        let width = (self.area.width - self.gutter) as usize;
+        let cursor_line = self.lines[self.line_at(self.index).0].number;
+        let mut start = self.lines_start + self.lines[..self.view[0].min(self.lines.len())].iter().map(|l| l.text.len()).sum::<usize>();
+        let highlights = self.highlights(start);
+        let ruler = self.editorconfig.max_line_length.filter(|r| (self.offset..self.offset + width).contains(r));
+
+        for row in 0..self.area.height
+        {
+            let line = self.lines.get(self.view[0] + row as usize);
+
+            queue!(screen, MoveTo(self.area.x, self.area.y + row))?;
+
+            if self.gutter > 0
+            {
+                let gutter = self.gutter as usize - 1;
+                let number = match line
+                {
+                    None => String::new(),
+                    Some(line) if line.wrapped => self.wrap_marker.trim().to_string(),
+                    Some(line) => {
+                        let number = match self.numbers
+                        {
+                            TNumbers::Relative => line.number.abs_diff(cursor_line),
+                            TNumbers::Hybrid if line.number != cursor_line => line.number.abs_diff(cursor_line),
+                            _ => self.first_line() + line.number + 1,
+                        };
+                        number.to_string()
+                    },
+                };
+                queue!(screen, PrintStyledContent(theme.style(theme.gutter).apply(format!("{number:>gutter$} "))))?;
+            }
+
+            let line = match line
+            {
+                None => {
+                    queue!(
+                        screen,
+                        PrintStyledContent(theme.style(theme.gutter).apply("~")),
+                        PrintStyledContent(theme.text.apply(" ".repeat(width.saturating_sub(1)))),
+                    )?;
+                    continue;
+                },
+                Some(line) => line,
+            };
+
+            let text = line.text.trim_end_matches('\n');
+            let mut run = String::new();
+            let mut run_style = theme.text;
+            let mut ruled = None;
+            let mut column = line.indent;
+            if line.indent > 0
+            {
+                let marker: String = match self.numbers
+                {
+                    TNumbers::Off => self.wrap_marker.chars().take(line.indent).collect(),
+                    _ => String::new(),
+                };
+                queue!(screen, PrintStyledContent(theme.style(theme.gutter).apply(format!("{marker:>0$}", line.indent))))?;
+            }
+            let mut cut = false;
+            for (i, c) in text.char_indices()
+            {
+                if column >= self.offset + width
+                {
+                    cut = true;
+                    break;
+                }
+
+                let span = char_width(c, column, self.tab_width);
+                if column + span <= self.offset
+                {
+                    column += span;
+                    continue;
+                }
+
+                let style = highlights.iter()
+                    .find(|([s, e], _)| start + i >= *s && start + i < *e)
+                    .map_or(theme.text, |(_, h)| theme.style(h.style(theme)));
+                if style != run_style && !run.is_empty()
+                {
+                    queue!(screen, PrintStyledContent(run_style.apply(std::mem::take(&mut run))))?;
+                }
+                run_style = style;
+                if ruler.is_some_and(|r| (column..column + span).contains(&r))
+                {
+                    ruled = Some((if c == '\t' { ' ' } else { printable(c) }, style));
+                }
+                match c
+                {
+                    '\t' => {
+                        let visible = (column + span).min(self.offset + width) - column.max(self.offset);
+                        run.push_str(" ".repeat(visible).as_str());
+                    },
+                    c => run.push(printable(c)),
+                }
+                column += span;
+            }
+            queue!(
+                screen,
+                PrintStyledContent(run_style.apply(run)),
+                PrintStyledContent(theme.text.apply(" ".repeat(width.saturating_sub(column.saturating_sub(self.offset))))),
+            )?;
+
+            if !self.wrap && self.offset > 0 && !text.is_empty()
+            {
+                queue!(
+                    screen,
+                    MoveTo(self.area.x + self.gutter, self.area.y + row),
+                    PrintStyledContent(theme.style(theme.gutter).apply('‹')),
+                )?;
+            }
+
+            if !self.wrap && cut
+            {
+                queue!(
+                    screen,
+                    MoveTo(self.area.x + self.gutter + width as u16 - 1, self.area.y + row),
+                    PrintStyledContent(theme.style(theme.gutter).apply('›')),
+                )?;
+            }
+
+            if let Some(ruler) = ruler
+            {
+                let (c, mut style) = ruled.unwrap_or((' ', theme.text));
+                style.background_color = theme.ruler.background_color.or(style.background_color);
+                style.attributes.extend(theme.ruler.attributes);
+                queue!(
+                    screen,
+                    MoveTo(self.area.x + self.gutter + (ruler - self.offset) as u16, self.area.y + row),
+                    PrintStyledContent(style.apply(c)),
+                )?;
+            }
+
+            start += line.text.len();
+        }
+
+        Ok(())
+    }
+
+    pub fn draw_cursor(&self, screen: &mut impl Write) -> std::io::Result<()>
+    {
+        let (line, line_index) = self.line_at(self.index);
+        let column = self.row_column(line, self.index - line_index).saturating_sub(self.offset) as u16;
+        let row = line.saturating_sub(self.view[0]) as u16;
+        let width = self.area.width - self.gutter;
+
+        queue!(
+            screen,
+            MoveTo(self.area.x + self.gutter + column.min(width.saturating_sub(1)), self.area.y + row),
+        )
+    }
+
+    pub fn selection(&self) -> Option<[usize; 2]>
+    {
+        let anchor = floor_char_boundary(&self.content, self.anchor?.min(self.content.len()));
+        let (start, end) = (anchor.min(self.index), anchor.max(self.index));
+        let end = end + self.content[end..].chars().next().map_or(0, |c| c.len_utf8());
+
+        Some([start, end])
+    }
+
+    pub fn expand_selection(&mut self) -> std::io::Result<bool>
+    {
+        let current = self.selection().unwrap_or([self.index, self.index]);
+        if self.expansions.last() != Some(&current)
+        {
+            self.expansions = vec![current];
+        }
+
+        let [start, end] = current;
+        // only brackets still open at the selection, or opening right at it, can be around it
+        let line = self.content[..start].matches('\n').count();
+        let mut openers: Vec<usize> = self.brackets.pair(&self.content, self.syntax.as_mut(), line, start).open.iter().map(|(_, i)| *i).collect();
+        openers.push(start);
+        let pairs: Vec<[usize; 2]> = openers.into_iter().filter_map(|open| Some([open, self.partner(open).filter(|close| *close > open)?])).collect();
+        let [a, b] = pairs.iter()
+            .filter(|[open, close]| *open < start && end <= *close)
+            .min_by_key(|[open, close]| close - open)
+            .map_or([0, self.content.len()], |[open, close]| [open + 1, *close]);
+
+        // a string around the selection lies inside the brackets around it
+        let first = self.content[..a].matches('\n').count();
+        let literals = self.literals(first..self.content[..b].matches('\n').count() + 1);
+        let inner = self.brackets.pair(&self.content, self.syntax.as_mut(), first, b).pairs;
+
+        let mut candidates = vec![[0, self.content.len()], line_span(&self.content, start, end)];
+        candidates.extend(word_range(&self.content, start));
+        for ([s, e], scope) in &literals
+        {
+            candidates.push([*s, *e]);
+            if *scope == TScope::String
+            {
+                candidates.extend(string_inner(&self.content, [*s, *e]));
+            }
+        }
+        let separator = |i: &usize| {
+            !inner.iter().any(|[open, close]| a <= *open && *open < *i && *i < *close)
+                && !literals.iter().any(|([s, e], _)| *s <= *i && *i < *e)
+        };
+        let before = self.content[a..start].rmatch_indices([',', ';']).map(|(i, _)| a + i).find(separator).map_or(a, |i| i + 1);
+        let after = self.content[end..b].match_indices([',', ';']).map(|(i, _)| end + i).find(separator).unwrap_or(b);
+        let segment = &self.content[before..after];
+        let trimmed = before + segment.len() - segment.trim_start().len();
+        candidates.push([trimmed, trimmed.max(after - (segment.len() - segment.trim_end().len()))]);
+
+        for [open, close] in pairs
+        {
+            candidates.push([open + 1, close]);
+            candidates.push([open, close + 1]);
+            candidates.push(line_span(&self.content, open, close + 1));
+
+            let callee = self.content[..open].trim_end_matches(|c: char| c.is_alphanumeric() || "_.:!".contains(c)).len();
+            if callee < open
+            {
+                candidates.push([callee, close + 1]);
+            }
+        }
+
+        let next = candidates.into_iter()
+            .filter(|[s, e]| *s <= start && end <= *e && e - s > end - start)
+            .min_by_key(|[s, e]| e - s);
+
+        match next
+        {
+            None => Ok(false),
+            Some(range) => {
+                self.expansions.push(range);
+                self.select(range)?;
+                Ok(true)
+            },
+        }
+    }
+
+    pub fn shrink_selection(&mut self) -> std::io::Result<bool>
+    {
+        let current = self.selection().unwrap_or([self.index, self.index]);
+        if self.expansions.len() < 2 || self.expansions.last() != Some(&current)
+        {
+            return Ok(false);
+        }
+
+        self.expansions.pop();
+        self.select(*self.expansions.last().unwrap())?;
+
+        Ok(true)
+    }
+
+    fn select(&mut self, range: [usize; 2]) -> std::io::Result<()>
+    {
+        let last = self.content[..range[1]].chars().next_back().map_or(range[0], |c| range[1] - c.len_utf8());
+        self.anchor = Some(range[0]);
+
+        self.move_to(last.max(range[0]))
+    }
+
+    pub fn match_bracket(&mut self, index: usize) -> Option<usize>
+    {
+        let end = self.content[index..].find('\n').map_or(self.content.len(), |i| index + i);
+        let positions: Vec<usize> = self.content[index..end].char_indices().map(|(i, _)| index + i).collect();
+
+        positions.into_iter().find_map(|i| self.partner(i))
+    }
+
+    pub fn enclosing_pair(&mut self, index: usize, open: char) -> Option<[usize; 2]>
+    {
+        self.brackets.enclosing(&self.content, self.syntax.as_mut(), index, open)
+    }
+
+    fn partner(&mut self, index: usize) -> Option<usize>
+    {
+        self.brackets.partner(&self.content, self.syntax.as_mut(), index)
+    }
+
+    fn literals(&mut self, lines: Range<usize>) -> Vec<([usize; 2], TScope)>
+    {
+        match self.syntax.as_mut()
+        {
+            None => Vec::new(),
+            Some(syntax) => syntax.literals(&self.content, lines),
+        }
+    }
+
+    fn highlights(&mut self, start: usize) -> Vec<([usize; 2], THighlight)>
+    {
+        let mut highlights: Vec<([usize; 2], THighlight)> = Vec::new();
+
+        if let Some(selection) = self.selection()
+        {
+            highlights.push((selection, THighlight::Selection));
+        }
+
+        let first = self.view[0].min(self.lines.len());
+        let visible = &self.lines[first..(first + self.area.height as usize).min(self.lines.len())];
+        let end: usize = start + visible.iter().map(|l| l.text.len()).sum::<usize>();
+        let numbers = visible.first().zip(visible.last()).map(|(first, last)| first.number..last.number + 1);
+
+        if !self.highlight.is_empty()
+        {
+            let from = floor_char_boundary(&self.content, start.saturating_sub(self.highlight.len() - 1));
+            let to = floor_char_boundary(&self.content, (end + self.highlight.len()).min(self.content.len()));
+
+            highlights.extend(
+                self.content[from..to].match_indices(self.highlight.as_str())
+                    .map(|(i, m)| ([from + i, from + i + m.len()], THighlight::Search))
+            );
+        }
+
+        let index = self.index;
+        if let Some(partner) = self.partner(index)
+        {
+            highlights.push(([index, index + 1], THighlight::Match));
+            highlights.push(([partner, partner + 1], THighlight::Match));
+        }
+        if let Some(numbers) = numbers.clone()
+        {
+            // only pair the visible lines, a bracket left open is unmatched once the end of the file is in view
+            let to = end.min(self.content.len());
+            let found = self.brackets.pair(&self.content, self.syntax.as_mut(), numbers.start, to);
+            let open = found.open.iter().map(|(_, i)| *i).filter(|_| to == self.content.len());
+            highlights.extend(
+                found.unmatched.iter().copied().chain(open).filter(|i| (start..end).contains(i)).map(|i| ([i, i + 1], THighlight::Mismatch))
+            );
+        }
+
+        if let (Some(syntax), true, Some(numbers)) = (self.syntax.as_mut(), self.colors, numbers)
+        {
+            highlights.extend(
+                syntax.highlight(&self.content, numbers).into_iter()
+                    .map(|(range, scope)| (range, THighlight::Syntax(scope)))
+            );
+        }
+
+        highlights
+    }
+
+    fn line_at(&self, index: usize) -> (usize, usize)
+    {
+        let mut line_index: usize = self.lines_start;
+        for (i, line) in self.lines.iter().enumerate()
+        {
+            if line_index + line.text.len() > index || i == self.lines.len() - 1
+            {
+                return (i, line_index);
+            }
+            line_index += line.text.len();
+        }
+
+        (0, 0)
+    }
+
+    fn view_changed(&mut self) -> std::io::Result<()>
+    {
+        self.shift()?;
+
+        self.make_view()
+    }
+
+    fn shift(&mut self) -> std::io::Result<()>
+    {
+        let row = self.line_at(self.index).0.saturating_sub(self.view[0]);
+
+        if self.shift_window()?
+        {
+            self.make_lines();
+            self.view[0] = self.line_at(self.index).0.saturating_sub(row);
+        }
+        else if self.index < self.lines_start || (self.index >= self.lines_end && self.lines_end < self.content.len())
+        {
+            self.make_lines();
+        }
+
+        Ok(())
+    }
+
+    fn shift_window(&mut self) -> std::io::Result<bool>
+    {
+        let large = match self.large.as_mut()
+        {
+            None => return Ok(false),
+            Some(l) => l,
+        };
+
+        let file = match self.file.as_mut()
+        {
+            None => return Ok(false),
+            Some(f) => f,
+        };
+
+        let line = self.content[..self.index].matches('\n').count();
+        let half = WINDOW_LINES / 2;
+        let margin = WINDOW_LINES / 4;
+        let absolute_index = large.start + self.index as u64;
+
+        // move by lines, but never start more than half a window of bytes before the cursor
+        let (first_line, start) = if large.end < large.len
+            && (line + margin >= WINDOW_LINES || (large.capped && self.content.len() - self.index < WINDOW_BYTES / 4))
+        {
+            let skip = line.saturating_sub(half);
+            let offset = match skip
+            {
+                0 => 0,
+                _ => self.content.match_indices('\n').nth(skip - 1).map_or(0, |(i, _)| i + 1),
+            };
+            if self.index - offset <= WINDOW_BYTES / 2
+            {
+                (large.first_line + skip, large.start + offset as u64)
+            }
+            else
+            {
+                let offset = self.index - WINDOW_BYTES / 2;
+                let skipped = self.content.as_bytes()[..offset].iter().filter(|b| **b == b'\n').count();
+                (large.first_line + skipped, large.start + offset as u64)
+            }
+        }
+        else if large.start > 0 && line < margin && self.index < WINDOW_BYTES / 4
+        {
+            let (first_line, start) = large.seek_line(file, (large.first_line + line).saturating_sub(half))?;
+            if absolute_index - start <= (WINDOW_BYTES / 2) as u64
+            {
+                (first_line, start)
+            }
+            else
+            {
+                let start = absolute_index - (WINDOW_BYTES / 2) as u64;
+                (large.first_line - large.count_lines(file, start, large.start)?, start)
+            }
+        }
+        else
+        {
+            return Ok(false);
+        };
+
+        if start == large.start
+        {
+            return Ok(false);
+        }
+
+        self.content = large.load(file, first_line, start)?;
+        self.brackets = TBrackets::default();
+        self.index = ((absolute_index - large.start) as usize).min(self.content.len());
+
+        Ok(true)
+    }
+
+    fn make_view(&mut self) -> std::io::Result<()>
+    {
+        let rows = self.area.height as usize;
+        let (line, _) = self.line_at(self.index);
+
+        if line < self.view[0]
+        {
+            self.view[0] = line;
+        }
+        else if line >= self.view[0] + rows
+        {
+            self.view[0] = line + 1 - rows;
+        }
+        self.view[0] = self.view[0].min(self.lines.len().saturating_sub(1));
+        self.view[1] = self.view[0] + rows;
+
+        if self.wrap
+        {
+            self.offset = 0;
+            return Ok(());
+        }
+
+        // scroll half a screen so the cursor never sits under a cut off marker
+        let width = (self.area.width - self.gutter) as usize;
+        let (line, line_index) = self.line_at(self.index);
+        let column = self.row_column(line, self.index - line_index);
+        if self.offset > 0 && column <= self.offset
+        {
+            self.offset = column.saturating_sub(width / 2);
+        }
+        else if column + 1 >= self.offset + width
+        {
+            self.offset = column + 1 + width / 2 - width;
+        }
+
+        Ok(())
+    }
+
+    pub fn first_line(&self) -> usize
+    {
+        match &self.large
+        {
+            None => 0,
+            Some(large) => large.first_line,
+        }
+    }
+
+    fn make_lines(&mut self)
+    {
+        self.gutter = match self.numbers
+        {
+            TNumbers::Off => 0,
+            _ => {
+                let count = match &self.large
+                {
+                    Some(large) => large.line_count().max(large.first_line + WINDOW_LINES),
+                    None => self.content.matches('\n').count() + 1,
+                };
+                (count.to_string().len() as u16 + 1).min(self.area.width / 2)
+            },
+        };
+
+        let cols = ((self.area.width - self.gutter) as usize).max(1);
+        let marker = if self.numbers == TNumbers::Off { self.wrap_marker.chars().count() } else { 0 };
+
+        let top = self.lines_start + self.lines[..self.view[0].min(self.lines.len())].iter().map(|l| l.text.len()).sum::<usize>();
+        let (first, [start, end]) = self.layout_range();
+        self.lines_start = start;
+        self.lines_end = end;
+        self.lines.clear();
+
+        // a partial layout ends after a newline that split() would turn into an empty line
+        let text = &self.content[start..end];
+        let text = if end < self.content.len() { &text[..text.len() - 1] } else { text };
+        for (number, mut line) in text.split('\n').enumerate()
+        {
+            let number = first + number;
+            let indent = if self.wrap_indent { display_width(leading_whitespace(line), self.tab_width) } else { 0 };
+            let prefix = (indent + marker).min(cols / 2);
+
+            let mut wrapped = false;
+            let mut start = 0;
+            while let Some(index) = wrap_index(line, start, cols, self.tab_width, self.word_wrap).filter(|_| self.wrap)
+            {
+                let (s1, s2) = line.split_at(index);
+                self.lines.push(TLine { text: s1.to_string(), number, wrapped, indent: start });
+                wrapped = true;
+                start = prefix;
+                line = s2;
+            }
+
+            self.lines.push(TLine { text: format!("{line}\n"), number, wrapped, indent: start });
+        }
+
+        if self.large.is_some()
+        {
+            self.view[0] = if (self.lines_start..self.lines_end).contains(&top) { self.line_at(top).0 } else { 0 };
+        }
+    }
+
+    // large files only lay out the lines a screen above and below the cursor
+    fn layout_range(&self) -> (usize, [usize; 2])
+    {
+        if self.large.is_none()
+        {
+            return (0, [0, self.content.len()]);
+        }
+
+        let rows = self.area.height as usize;
+        let mut start = self.content[..self.index].rfind('\n').map_or(0, |i| i + 1);
+        let mut first = self.content[..start].matches('\n').count();
+        for _ in 0..rows
+        {
+            if start == 0
+            {
+                break;
+            }
+            start = self.content[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+            first -= 1;
+        }
+        let end = self.content[self.index..].match_indices('\n').nth(rows).map_or(self.content.len(), |(i, _)| self.index + i + 1);
+
+        (first, [start, end])
+    }
+}
+
+
+fn detect_indent(content: &str) -> Option<String>
+{
+    let mut tabs = 0;
+    let mut spaces = 0;
+    let mut steps = [0; 9];
+    let mut previous = 0;
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()).take(1000)
+    {
+        if line.starts_with('\t')
+        {
+            tabs += 1;
+            previous = 0;
+            continue;
+        }
+
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 0
+        {
+            spaces += 1;
+        }
+        if indent > previous && indent - previous < steps.len()
+        {
+            steps[indent - previous] += 1;
+        }
+        previous = indent;
+    }
+
+    // one indented line is too little to tell a unit from a single deep indent
+    if tabs + spaces < 2
+    {
+        return None;
+    }
+    if tabs > spaces
+    {
+        return Some(String::from("\t"));
+    }
+
+    let width = (2..steps.len()).rev().max_by_key(|w| steps[*w])?;
+    (steps[width] > 0).then(|| " ".repeat(width))
+}
+
+
+fn printable(c: char) -> char
+{
+    if c.is_control() { '?' } else { c }
+}
+
+
+fn char_width(c: char, column: usize, tab_width: usize) -> usize
+{
+    if c == '\t' { tab_width - column % tab_width } else { 1 }
+}
+
+
+fn display_width(text: &str, tab_width: usize) -> usize
+{
+    text.chars().fold(0, |column, c| column + char_width(c, column, tab_width))
+}
+
+
+fn display_index(line: &str, column: usize, tab_width: usize) -> usize
+{
+    let mut width = 0;
+    for (i, c) in line.char_indices()
+    {
+        width += char_width(c, width, tab_width);
+        if c == '\n' || width > column
+        {
+            return i;
+        }
+    }
+
+    line.len()
+}
+
+
+fn leading_whitespace(line: &str) -> &str
+{
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+
+fn wrap_index(line: &str, start: usize, cols: usize, tab_width: usize, words: bool) -> Option<usize>
+{
+    let mut width = start;
+    for (i, c) in line.char_indices()
+    {
+        width += char_width(c, width, tab_width);
+        if width <= cols
+        {
+            continue;
+        }
+
+        if !words
+        {
+            return Some(if i == 0 { c.len_utf8() } else { i });
+        }
+
+        // spaces may hang past the edge so the next row starts with a word
+        if c == ' ' || c == '\t'
+        {
+            return Some(i + 1);
+        }
+
+        return match line[..i].rfind([' ', '\t'])
+        {
+            Some(space) if space >= leading_whitespace(line).len() => Some(space + 1),
+            _ if i == 0 => Some(c.len_utf8()),
+            _ => Some(i),
+        };
+    }
+
+    None
+}
+
+
+fn column_index(line: &str, column: usize) -> usize
+{
+    match line.char_indices().nth(column)
+    {
+        None => line.trim_end_matches('\n').len(),
+        Some((i, _)) => i,
+    }
+}
+
+
+fn word_range(content: &str, index: usize) -> Option<[usize; 2]>
+{
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = content[..index].trim_end_matches(is_word).len();
+    let end = index + content[index..].find(|c: char| !is_word(c)).unwrap_or(content.len() - index);
+
+    (start < end).then_some([start, end])
+}
+
+
+fn line_span(content: &str, start: usize, end: usize) -> [usize; 2]
+{
+    let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+    let indent = content[line_start..].len() - content[line_start..].trim_start_matches([' ', '\t']).len();
+    let last = end.saturating_sub(1).max(start);
+    let line_end = content[last..].find('\n').map_or(content.len(), |i| last + i);
+
+    [if line_start + indent <= start { line_start + indent } else { line_start }, line_end.max(end)]
+}
+
+
+fn string_inner(content: &str, range: [usize; 2]) -> Option<[usize; 2]>
+{
+    let text = &content[range[0]..range[1]];
+    let quote = |c: char| "\"'`#".contains(c);
+    let open = text.find(quote)?;
+    let open = open + text[open..].find(|c: char| !quote(c)).unwrap_or(text.len() - open);
+    let close = text.trim_end_matches(quote).len();
+
+    (open <= close).then_some([range[0] + open, range[0] + close])
+}
+
+
+fn floor_char_boundary(string: &str, index: usize) -> usize
+{
+    let mut index = index.min(string.len());
+    while !string.is_char_boundary(index)
+    {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn detects_indent_units()
+    {
+        assert_eq!(detect_indent("fn a()\n{\n    b\n    {\n        c\n    }\n}\n").as_deref(), Some("    "));
+        assert_eq!(detect_indent("a:\n  b:\n    c\n").as_deref(), Some("  "));
+        assert_eq!(detect_indent("a\n\tb\n\t\tc\n").as_deref(), Some("\t"));
+        assert_eq!(detect_indent("a\n        x\n"), None);
+        assert_eq!(detect_indent("a\n\tx\n"), None);
+        assert_eq!(detect_indent("a\nb\n"), None);
+    }
+
+    #[test]
+    fn wraps_only_past_the_width()
+    {
+        assert_eq!(wrap_index("abcd", 0, 4, 4, true), None);
+        assert_eq!(wrap_index("", 0, 4, 4, true), None);
+        assert_eq!(wrap_index("abcde", 0, 4, 4, false), Some(4));
+    }
+
+    #[test]
+    fn wraps_after_spaces()
+    {
+        assert_eq!(wrap_index("hello world", 0, 8, 4, true), Some(6));
+        assert_eq!(wrap_index("hello world", 0, 8, 4, false), Some(8));
+        assert_eq!(wrap_index("hello   world", 0, 6, 4, true), Some(7));
+        assert_eq!(wrap_index("abcdefghij", 0, 4, 4, true), Some(4));
+    }
+
+    #[test]
+    fn keeps_leading_indent_on_the_first_row()
+    {
+        assert_eq!(wrap_index("    abcdefgh", 0, 6, 4, true), Some(6));
+    }
+
+    #[test]
+    fn measures_tabs_and_continued_rows()
+    {
+        assert_eq!(wrap_index("a\tbc", 0, 4, 4, false), Some(2));
+        assert_eq!(wrap_index("\tab", 0, 5, 4, false), Some(2));
+        assert_eq!(wrap_index("ab", 3, 4, 4, true), Some(1));
+        assert_eq!(wrap_index("ab", 4, 4, 4, true), Some(1));
     }
 }
\ No newline at end of file
@@ -1,35 +1,97 @@
 use std::fs::read_dir;
-use std::io::stdout;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use crossterm::{
     execute, ExecutableCommand,
-    cursor::{MoveTo, RestorePosition, SavePosition, position, MoveDown, MoveUp},
+    cursor::MoveTo,
+    event::{Event, read, KeyEventKind},
+    style::{PrintStyledContent, SetStyle},
     terminal::{Clear, ClearType, size},
 };
 
+use crate::t_options::TExplorerOptions;
+use crate::t_keymap::{TContext, TKeyAction, TKeymap, TLookup};
+use crate::t_screen::screen;
+use crate::t_theme::TTheme;
+
 
 pub struct TFileExplorer
 {
     pub directory: String,
     pub paths: Vec<PathBuf>,
     pub view: [usize; 2],
+    pub cursor: u16,
+    pub hidden: bool,
+    pub dirs_first: bool,
+    pub theme: TTheme,
 }
 
 
 impl TFileExplorer
 {
-    pub fn new() -> TFileExplorer
+    pub fn new(options: &TExplorerOptions, theme: TTheme) -> TFileExplorer
     {
-        let mut t_file_explorer = TFileExplorer { directory: String::from("./"), paths: Vec::new(), view: [0, 0] };
+        let directory = match (options.directory.strip_prefix('~'), std::env::var("HOME"))
+        {
+            (Some(rest), Ok(home)) => format!("{home}{rest}"),
+            _ => options.directory.clone(),
+        };
+        let directory = if Path::new(directory.as_str()).is_dir() { directory } else { String::from("./") };
+
+        let mut t_file_explorer = TFileExplorer { directory,
+            paths: Vec::new(),
+            view: [0, 0],
+            cursor: 1,
+            hidden: options.hidden,
+            dirs_first: options.dirs_first,
+            theme,
+        };
         t_file_explorer.make_paths();
         t_file_explorer
     }
 
-    pub fn select(&mut self) -> Option<&str>
+    pub fn pick(&mut self, keymap: &TKeymap) -> Option<String>
     {
-        let (_, y) = position().unwrap();
+        let mut sequence = Vec::new();
+        loop
+        {
+            if let Event::Key(event) = read().unwrap()
+            {
+                if event.kind == KeyEventKind::Press
+                {
+                    sequence.push(event);
+                    let action = match keymap.lookup(&[TContext::Explorer], &sequence)
+                    {
+                        TLookup::Prefix => continue,
+                        TLookup::None => None,
+                        TLookup::Action(action) => Some(action),
+                    };
+                    sequence.clear();
+
+                    match action
+                    {
+                        Some(TKeyAction::Quit) => return None,
+
+                        Some(TKeyAction::Up) => { self.move_up().unwrap(); },
+                        Some(TKeyAction::Down) => { self.move_down().unwrap(); },
+                        Some(TKeyAction::Select) => {
+                            match self.select()
+                            {
+                                None => { self.make_paths(); },
+                                Some(path) => return Some(path.to_string()),
+                            };
+                        },
+                        Some(TKeyAction::Parent) => { self.back().unwrap(); },
+                        _ => {},
+                    }
+                }
+            }
+        }
+    }
 
-        let path = self.paths.get((y - 1) as usize)?.as_path();
+    pub fn select(&mut self) -> Option<&str>
+    {
+        let path = self.paths.get(self.view[0] + self.cursor as usize - 1)?.as_path();
 
         if path.is_dir()
         {
@@ -41,7 +103,7 @@ impl TFileExplorer
             return None;
         }
 
-        Some(path.to_str()?)
+        path.to_str()
     }
 
     pub fn back(&mut self) -> std::io::Result<()>
@@ -60,15 +122,10 @@ impl TFileExplorer
 
     pub fn move_up(&mut self) -> std::io::Result<()>
     {
-        let (_, y) = position()?;
-
-        if y > 1
+        if self.cursor > 1
         {
-            execute!(
-                stdout(),
-                MoveUp(1),
-                SavePosition,
-            )?;
+            self.cursor -= 1;
+            screen().execute(MoveTo(3, self.cursor))?;
         }
         else if self.view[0] > 0
         {
@@ -83,17 +140,13 @@ impl TFileExplorer
     pub fn move_down(&mut self) -> std::io::Result<()>
     {
         let (_, rows) = size()?;
-        let (_, y) = position()?;
 
-        if y < self.paths.len() as u16
+        if self.view[0] + (self.cursor as usize) < self.paths.len()
         {
-            if y < rows - 1
+            if self.cursor < rows - 1
             {
-                execute!(
-                    stdout(),
-                    MoveDown(1),
-                    SavePosition,
-                )?;
+                self.cursor += 1;
+                screen().execute(MoveTo(3, self.cursor))?;
             }
             else if self.view[1] < self.paths.len()
             {
@@ -108,45 +161,37 @@ impl TFileExplorer
 
     pub fn clear_screen(&mut self) -> std::io::Result<()>
     {
+        let mut screen = screen();
+
+        let theme = &self.theme;
         execute!(
-            stdout(),
+            screen,
+            SetStyle(theme.text),
             Clear(ClearType::All),
             Clear(ClearType::Purge),
             MoveTo(0, 0),
         )?;
 
+        write!(screen, "{}", Path::new(self.directory.as_str()).display())?;
+
         if self.paths.is_empty()
         {
-            println!("{}\n└──Empty Folder", Path::new(self.directory.as_str()).display());
+            execute!(screen, PrintStyledContent(theme.style(theme.tree).apply("\r\n└──")), PrintStyledContent(theme.style(theme.file).apply("Empty Folder")))?;
 
-            stdout().execute(RestorePosition)?;
+            screen.execute(MoveTo(3, self.cursor))?;
 
             return Ok(());
         }
 
-        println!("{}", Path::new(self.directory.as_str()).display());
-
         for (i, path) in self.paths[self.view[0]..self.view[1]].iter().enumerate()
         {
+            let style = if path.is_dir() { theme.directory } else { theme.file };
+            let branch = if i + self.view[0] < self.view[1] - 1 || self.view[1] < self.paths.len() { "\r\n├──" } else { "\r\n└──" };
             let path = path.file_name().unwrap().to_str().unwrap();
-            if i + self.view[0] < self.view[1] - 1
-            {
-                println!("├──{path}");
-            }
-            else
-            {
-                if self.view[1] == self.paths.len()
-                {
-                    print!("└──{path}");
-                }
-                else
-                {
-                    print!("├──{path}");
-                }
-            }
+            execute!(screen, PrintStyledContent(theme.style(theme.tree).apply(branch)), PrintStyledContent(theme.style(style).apply(path)))?;
         }
 
-        stdout().execute(RestorePosition)?;
+        screen.execute(MoveTo(3, self.cursor))?;
 
         Ok(())
     }
@@ -163,33 +208,35 @@ impl TFileExplorer
             },
         };
 
-        let _ = execute!(
-            stdout(),
-            MoveTo(3, 1),
-            SavePosition,
-        );
+        self.cursor = 1;
+        self.view[0] = 0;
 
         self.paths.clear();
 
         for path in dir
         {
             let s_path = path.iter().clone().next().unwrap();
-            match s_path.path().canonicalize()
+            if let Ok(p) = s_path.path().canonicalize()
             {
-                Ok(p) => {
-                    let path = &p.to_str().unwrap()[4..];
-                    self.paths.push(PathBuf::from(path));
-                    continue;
-                },
-                Err(_) => {},
-            };
+                let path = p.to_str().unwrap();
+                self.paths.push(PathBuf::from(path.strip_prefix(r"\\?\").unwrap_or(path)));
+                continue;
+            }
             self.paths.push(path.unwrap().path());
         }
 
-        match self.paths.get(0)
+        if !self.hidden
+        {
+            self.paths.retain(|p| !p.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')));
+        }
+        if self.dirs_first
+        {
+            self.paths.sort_by_key(|p| !p.is_dir());
+        }
+
+        if let Some(p) = self.paths.first()
         {
-            None => {},
-            Some(p) => self.directory = p.parent().unwrap().to_str().unwrap().to_string(),
+            self.directory = p.parent().unwrap().to_str().unwrap().to_string();
         }
 
         self.view[1] = self.paths.len();
@@ -0,0 +1,370 @@
+use std::fs;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::t_dirs::config_dir;
+use crate::t_keys::{describe_keys, parse_keys};
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TContext
+{
+    Global,
+    Edit,
+    View,
+    Command,
+    Explorer,
+}
+
+
+impl TContext
+{
+    pub const ALL: [TContext; 5] = [TContext::Global, TContext::Edit, TContext::View, TContext::Command, TContext::Explorer];
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            TContext::Global => "global",
+            TContext::Edit => "edit",
+            TContext::View => "view",
+            TContext::Command => "command",
+            TContext::Explorer => "explorer",
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TKeyAction
+{
+    Quit,
+    SaveAndExit,
+    Open,
+    NextBuffer,
+    PreviousBuffer,
+    PickBuffer,
+    Split,
+    VerticalSplit,
+    CloseWindow,
+    NextWindow,
+    WindowLeft,
+    WindowDown,
+    WindowUp,
+    WindowRight,
+    GrowHeight,
+    ShrinkHeight,
+    GrowWidth,
+    ShrinkWidth,
+    Equalize,
+    CommandLine,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Record,
+    Replay,
+    ExpandSelection,
+    ShrinkSelection,
+    MatchBracket,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Undo,
+    Newline,
+    Backspace,
+    Indent,
+    Run,
+    Cancel,
+    HistoryBack,
+    HistoryForward,
+    Complete,
+    CompleteBack,
+    Select,
+    Parent,
+}
+
+
+pub const ACTIONS: [(TKeyAction, &str, &str); 46] = [
+    (TKeyAction::Quit, "quit", "Exit, Asks To Save Changes"),
+    (TKeyAction::SaveAndExit, "save_and_exit", "Save And Exit"),
+    (TKeyAction::Open, "open", "Open File"),
+    (TKeyAction::NextBuffer, "next_buffer", "Next Buffer"),
+    (TKeyAction::PreviousBuffer, "previous_buffer", "Previous Buffer"),
+    (TKeyAction::PickBuffer, "pick_buffer", "Pick Buffer"),
+    (TKeyAction::Split, "split", "Split Window Horizontally"),
+    (TKeyAction::VerticalSplit, "vertical_split", "Split Window Vertically"),
+    (TKeyAction::CloseWindow, "close_window", "Close Window"),
+    (TKeyAction::NextWindow, "next_window", "Next Window"),
+    (TKeyAction::WindowLeft, "window_left", "Move To The Window On The Left"),
+    (TKeyAction::WindowDown, "window_down", "Move To The Window Below"),
+    (TKeyAction::WindowUp, "window_up", "Move To The Window Above"),
+    (TKeyAction::WindowRight, "window_right", "Move To The Window On The Right"),
+    (TKeyAction::GrowHeight, "grow_height", "Increase Window Height"),
+    (TKeyAction::ShrinkHeight, "shrink_height", "Decrease Window Height"),
+    (TKeyAction::GrowWidth, "grow_width", "Increase Window Width"),
+    (TKeyAction::ShrinkWidth, "shrink_width", "Decrease Window Width"),
+    (TKeyAction::Equalize, "equalize", "Equalize Windows"),
+    (TKeyAction::CommandLine, "command_line", "Command Line"),
+    (TKeyAction::Search, "search", "Search, Enter On An Empty Search Finds The Next Match"),
+    (TKeyAction::NextMatch, "next_match", "Next Match"),
+    (TKeyAction::PreviousMatch, "previous_match", "Previous Match"),
+    (TKeyAction::Record, "record", "Start Or Stop Recording A Macro"),
+    (TKeyAction::Replay, "replay", "Replay The Macro"),
+    (TKeyAction::ExpandSelection, "expand_selection", "Expand The Selection To The Enclosing Word, String Or Brackets"),
+    (TKeyAction::ShrinkSelection, "shrink_selection", "Shrink The Selection Back"),
+    (TKeyAction::MatchBracket, "match_bracket", "Jump To The Matching Bracket"),
+    (TKeyAction::Up, "up", "Move Up"),
+    (TKeyAction::Down, "down", "Move Down"),
+    (TKeyAction::Left, "left", "Move Left"),
+    (TKeyAction::Right, "right", "Move Right"),
+    (TKeyAction::PageUp, "page_up", "Move A Page Up"),
+    (TKeyAction::PageDown, "page_down", "Move A Page Down"),
+    (TKeyAction::Undo, "undo", "Undo"),
+    (TKeyAction::Newline, "newline", "New Line"),
+    (TKeyAction::Backspace, "backspace", "Delete The Previous Character Or Indent Level"),
+    (TKeyAction::Indent, "indent", "Indent To The Next Tab Stop"),
+    (TKeyAction::Run, "run", "Run"),
+    (TKeyAction::Cancel, "cancel", "Cancel"),
+    (TKeyAction::HistoryBack, "history_back", "Previous Command In The History"),
+    (TKeyAction::HistoryForward, "history_forward", "Next Command In The History"),
+    (TKeyAction::Complete, "complete", "Complete Command, Option Or File Path"),
+    (TKeyAction::CompleteBack, "complete_back", "Previous Completion"),
+    (TKeyAction::Select, "select", "Select"),
+    (TKeyAction::Parent, "parent", "Parent Directory"),
+];
+
+
+const DEFAULTS: [(TContext, &str, TKeyAction); 67] = [
+    (TContext::Global, "<C-s>", TKeyAction::SaveAndExit),
+    (TContext::Global, "<C-o>", TKeyAction::Open),
+    (TContext::Global, "<C-n>", TKeyAction::NextBuffer),
+    (TContext::Global, "<C-PageDown>", TKeyAction::NextBuffer),
+    (TContext::Global, "<C-p>", TKeyAction::PreviousBuffer),
+    (TContext::Global, "<C-PageUp>", TKeyAction::PreviousBuffer),
+    (TContext::Global, "<C-b>", TKeyAction::PickBuffer),
+    (TContext::Global, "<C-w>s", TKeyAction::Split),
+    (TContext::Global, "<C-w>v", TKeyAction::VerticalSplit),
+    (TContext::Global, "<C-w>q", TKeyAction::CloseWindow),
+    (TContext::Global, "<C-w>c", TKeyAction::CloseWindow),
+    (TContext::Global, "<C-w>w", TKeyAction::NextWindow),
+    (TContext::Global, "<C-w>h", TKeyAction::WindowLeft),
+    (TContext::Global, "<C-w><Left>", TKeyAction::WindowLeft),
+    (TContext::Global, "<C-w>j", TKeyAction::WindowDown),
+    (TContext::Global, "<C-w><Down>", TKeyAction::WindowDown),
+    (TContext::Global, "<C-w>k", TKeyAction::WindowUp),
+    (TContext::Global, "<C-w><Up>", TKeyAction::WindowUp),
+    (TContext::Global, "<C-w>l", TKeyAction::WindowRight),
+    (TContext::Global, "<C-w><Right>", TKeyAction::WindowRight),
+    (TContext::Global, "<C-w>+", TKeyAction::GrowHeight),
+    (TContext::Global, "<C-w>-", TKeyAction::ShrinkHeight),
+    (TContext::Global, "<C-w>>", TKeyAction::GrowWidth),
+    (TContext::Global, "<C-w><lt>", TKeyAction::ShrinkWidth),
+    (TContext::Global, "<C-w>=", TKeyAction::Equalize),
+    (TContext::Global, "<C-e>", TKeyAction::CommandLine),
+    (TContext::Global, "<C-f>", TKeyAction::Search),
+    (TContext::Global, "<F3>", TKeyAction::Record),
+    (TContext::Global, "<F4>", TKeyAction::Replay),
+    (TContext::Global, "<A-Up>", TKeyAction::ExpandSelection),
+    (TContext::Global, "<A-Down>", TKeyAction::ShrinkSelection),
+    (TContext::Global, "<C-]>", TKeyAction::MatchBracket),
+    (TContext::Edit, "<Esc>", TKeyAction::Quit),
+    (TContext::Edit, "<End>", TKeyAction::Quit),
+    (TContext::Edit, "<Del>", TKeyAction::Quit),
+    (TContext::Edit, "<Up>", TKeyAction::Up),
+    (TContext::Edit, "<Down>", TKeyAction::Down),
+    (TContext::Edit, "<Left>", TKeyAction::Left),
+    (TContext::Edit, "<Right>", TKeyAction::Right),
+    (TContext::Edit, "<PageUp>", TKeyAction::PageUp),
+    (TContext::Edit, "<PageDown>", TKeyAction::PageDown),
+    (TContext::Edit, "<C-z>", TKeyAction::Undo),
+    (TContext::Edit, "<CR>", TKeyAction::Newline),
+    (TContext::Edit, "<BS>", TKeyAction::Backspace),
+    (TContext::Edit, "<Tab>", TKeyAction::Indent),
+    (TContext::View, "q", TKeyAction::Quit),
+    (TContext::View, "<Space>", TKeyAction::PageDown),
+    (TContext::View, "b", TKeyAction::PageUp),
+    (TContext::View, ":", TKeyAction::CommandLine),
+    (TContext::View, "/", TKeyAction::Search),
+    (TContext::View, "n", TKeyAction::NextMatch),
+    (TContext::View, "N", TKeyAction::PreviousMatch),
+    (TContext::Command, "<CR>", TKeyAction::Run),
+    (TContext::Command, "<Esc>", TKeyAction::Cancel),
+    (TContext::Command, "<Up>", TKeyAction::HistoryBack),
+    (TContext::Command, "<Down>", TKeyAction::HistoryForward),
+    (TContext::Command, "<Tab>", TKeyAction::Complete),
+    (TContext::Command, "<S-Tab>", TKeyAction::CompleteBack),
+    (TContext::Explorer, "<Esc>", TKeyAction::Quit),
+    (TContext::Explorer, "<End>", TKeyAction::Quit),
+    (TContext::Explorer, "<Del>", TKeyAction::Quit),
+    (TContext::Explorer, "<C-s>", TKeyAction::Quit),
+    (TContext::Explorer, "<Up>", TKeyAction::Up),
+    (TContext::Explorer, "<Down>", TKeyAction::Down),
+    (TContext::Explorer, "<CR>", TKeyAction::Select),
+    (TContext::Explorer, "<Space>", TKeyAction::Select),
+    (TContext::Explorer, "<BS>", TKeyAction::Parent),
+];
+
+
+pub enum TLookup
+{
+    None,
+    Prefix,
+    Action(TKeyAction),
+}
+
+
+pub struct TKeymap
+{
+    bindings: Vec<(TContext, Vec<KeyEvent>, TKeyAction)>,
+}
+
+
+impl TKeymap
+{
+    pub fn new() -> TKeymap
+    {
+        let bindings = DEFAULTS.iter()
+            .map(|(context, keys, action)| (*context, parse_keys(keys).unwrap(), *action))
+            .collect();
+
+        TKeymap { bindings }
+    }
+
+    pub fn load() -> (TKeymap, Option<String>)
+    {
+        let mut t_keymap = TKeymap::new();
+
+        let content = match config_dir().map(|dir| fs::read_to_string(dir.join("keys")))
+        {
+            Some(Ok(content)) => content,
+            _ => return (t_keymap, None),
+        };
+
+        let mut error = None;
+        for (i, line) in content.lines().enumerate()
+        {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#')
+            {
+                continue;
+            }
+
+            if let Err(err) = t_keymap.apply(line)
+            {
+                error.get_or_insert(format!("keys line {}: {err}", i + 1));
+            }
+        }
+
+        (t_keymap, error)
+    }
+
+    fn apply(&mut self, line: &str) -> Result<(), &'static str>
+    {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let [context, keys, action] = words[..] else {
+            return Err("Expected CONTEXT KEYS ACTION.");
+        };
+
+        let context = *TContext::ALL.iter().find(|c| c.name() == context).ok_or("Unknown context.")?;
+        let keys = parse_keys(keys)?;
+        if keys.is_empty()
+        {
+            return Err("Missing keys.");
+        }
+
+        self.bindings.retain(|(c, k, _)| *c != context || !same_keys(k, &keys));
+
+        if action != "none"
+        {
+            let action = ACTIONS.iter().find(|(_, name, _)| *name == action).ok_or("Unknown action.")?.0;
+            self.bindings.push((context, keys, action));
+        }
+
+        Ok(())
+    }
+
+    pub fn lookup(&self, contexts: &[TContext], keys: &[KeyEvent]) -> TLookup
+    {
+        let mut prefix = false;
+        for context in contexts
+        {
+            for (c, k, action) in &self.bindings
+            {
+                if c != context || k.len() < keys.len() || !same_keys(&k[..keys.len()], keys)
+                {
+                    continue;
+                }
+                if k.len() == keys.len()
+                {
+                    return TLookup::Action(*action);
+                }
+                prefix = true;
+            }
+        }
+
+        if prefix { TLookup::Prefix } else { TLookup::None }
+    }
+
+    pub fn describe(&self, context: TContext) -> String
+    {
+        self.describe_with(context, false)
+    }
+
+    // normal and visual mode take plain characters before the global bindings do
+    pub fn describe_modal(&self) -> String
+    {
+        self.describe_with(TContext::Global, true)
+    }
+
+    fn describe_with(&self, context: TContext, modal: bool) -> String
+    {
+        let mut lines = String::new();
+        for (action, _, description) in ACTIONS
+        {
+            let keys: Vec<String> = self.bindings.iter()
+                .filter(|(c, _, a)| *c == context && *a == action)
+                .map(|(_, k, _)| if modal && is_plain(&k[0]) { format!("{} (Insert Mode)", describe_keys(k)) } else { describe_keys(k) })
+                .collect();
+
+            let description = if action == TKeyAction::Quit && matches!(context, TContext::View | TContext::Explorer) { "Exit" } else { description };
+            if !keys.is_empty()
+            {
+                lines.push_str(format!("    {} => {description}\n", keys.join(", ")).as_str());
+            }
+        }
+
+        lines
+    }
+}
+
+
+pub fn is_plain(key: &KeyEvent) -> bool
+{
+    matches!(key.code, KeyCode::Char(_)) && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+
+fn normalize(key: &KeyEvent) -> (KeyCode, KeyModifiers)
+{
+    let mut modifiers = key.modifiers;
+    if matches!(key.code, KeyCode::Char(_) | KeyCode::BackTab)
+    {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+
+    // Terminals send Ctrl-\ ] ^ _ as the same bytes as Ctrl-4 to Ctrl-7.
+    let code = match key.code
+    {
+        KeyCode::Char(c @ '4'..='7') if modifiers == KeyModifiers::CONTROL => KeyCode::Char(['\\', ']', '^', '_'][c as usize - '4' as usize]),
+        code => code,
+    };
+    (code, modifiers)
+}
+
+
+fn same_keys(a: &[KeyEvent], b: &[KeyEvent]) -> bool
+{
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| normalize(a) == normalize(b))
+}
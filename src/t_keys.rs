@@ -0,0 +1,217 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+
+const NAMES: [(&str, KeyCode); 16] = [
+    ("Esc", KeyCode::Esc),
+    ("CR", KeyCode::Enter),
+    ("BS", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("Del", KeyCode::Delete),
+    ("Ins", KeyCode::Insert),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Space", KeyCode::Char(' ')),
+    ("lt", KeyCode::Char('<')),
+];
+
+
+pub fn format_key(key: &KeyEvent) -> Option<String>
+{
+    let mut modifiers = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL)
+    {
+        modifiers.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT)
+    {
+        modifiers.push_str("A-");
+    }
+    // shifted characters arrive as their uppercase or symbol form already
+    if key.modifiers.contains(KeyModifiers::SHIFT) && !matches!(key.code, KeyCode::Char(_) | KeyCode::BackTab)
+    {
+        modifiers.push_str("S-");
+    }
+
+    let name = match key.code
+    {
+        KeyCode::Char(c) if modifiers.is_empty() && c != '<' => return Some(c.to_string()),
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char('<') => String::from("lt"),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::BackTab => String::from("S-Tab"),
+        KeyCode::F(n) => format!("F{n}"),
+        code => NAMES.iter().find(|(_, c)| *c == code)?.0.to_string(),
+    };
+
+    Some(format!("<{modifiers}{name}>"))
+}
+
+
+pub fn format_keys(keys: &[KeyEvent]) -> Result<String, &'static str>
+{
+    keys.iter().map(format_key).collect::<Option<String>>().ok_or("Macro contains a key that has no name.")
+}
+
+
+pub fn parse_key(name: &str) -> Option<KeyEvent>
+{
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop
+    {
+        let lower = rest.to_ascii_lowercase();
+        if rest.len() > 2 && lower.starts_with("c-")
+        {
+            modifiers |= KeyModifiers::CONTROL;
+        }
+        else if rest.len() > 2 && (lower.starts_with("a-") || lower.starts_with("m-"))
+        {
+            modifiers |= KeyModifiers::ALT;
+        }
+        else if rest.len() > 2 && lower.starts_with("s-")
+        {
+            modifiers |= KeyModifiers::SHIFT;
+        }
+        else
+        {
+            break;
+        }
+        rest = &rest[2..];
+    }
+
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next())
+    {
+        (Some(c), None) => {
+            let c = if modifiers.contains(KeyModifiers::CONTROL) { c.to_ascii_lowercase() } else { c };
+            KeyCode::Char(c)
+        },
+        _ if rest.eq_ignore_ascii_case("tab") && modifiers.contains(KeyModifiers::SHIFT) => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            KeyCode::BackTab
+        },
+        _ if rest.eq_ignore_ascii_case("enter") || rest.eq_ignore_ascii_case("return") => KeyCode::Enter,
+        _ if rest.eq_ignore_ascii_case("escape") => KeyCode::Esc,
+        _ if rest.eq_ignore_ascii_case("backspace") => KeyCode::Backspace,
+        _ if rest.eq_ignore_ascii_case("delete") => KeyCode::Delete,
+        _ if rest.len() > 1 && rest[..1].eq_ignore_ascii_case("f") && rest[1..].parse::<u8>().is_ok() => {
+            KeyCode::F(rest[1..].parse().unwrap())
+        },
+        _ => NAMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(rest))?.1,
+    };
+
+    Some(KeyEvent::new(code, modifiers))
+}
+
+
+pub fn parse_keys(notation: &str) -> Result<Vec<KeyEvent>, &'static str>
+{
+    let mut keys = Vec::new();
+    let mut rest = notation;
+
+    while let Some(c) = rest.chars().next()
+    {
+        if c == '<'
+        {
+            if let Some(end) = rest.find('>').filter(|e| *e > 1)
+            {
+                keys.push(parse_key(&rest[1..end]).ok_or("Unknown key name.")?);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+
+        if c != '\n' && c != '\r'
+        {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Ok(keys)
+}
+
+
+pub fn describe_key(key: &KeyEvent) -> String
+{
+    let mut name = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL)
+    {
+        name.push_str("Ctrl-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT)
+    {
+        name.push_str("Alt-");
+    }
+
+    match key.code
+    {
+        KeyCode::Char(' ') => name.push_str("Space"),
+        KeyCode::Char(c) if c.is_uppercase() => name.push_str(format!("Shift-{c}").as_str()),
+        KeyCode::Char(c) => name.push_str(c.to_uppercase().to_string().as_str()),
+        KeyCode::Enter => name.push_str("Enter"),
+        KeyCode::Backspace => name.push_str("Backspace"),
+        KeyCode::Delete => name.push_str("Delete"),
+        KeyCode::BackTab => name.push_str("Shift-Tab"),
+        KeyCode::PageUp => name.push_str("Page Up"),
+        KeyCode::PageDown => name.push_str("Page Down"),
+        KeyCode::F(n) => name.push_str(format!("F{n}").as_str()),
+        code => name.push_str(NAMES.iter().find(|(_, c)| *c == code).map_or("Nop", |(n, _)| n)),
+    }
+
+    name
+}
+
+
+pub fn describe_keys(keys: &[KeyEvent]) -> String
+{
+    keys.iter().map(describe_key).collect::<Vec<String>>().join(" ")
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parses_names_and_modifiers()
+    {
+        let keys = parse_keys("<C-A><S-Up><S-Tab><lt><Space><CR><F5><M-x>a\n").unwrap();
+        assert_eq!(keys, vec![
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL),
+            KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT),
+            KeyEvent::new(KeyCode::BackTab, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE),
+            KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT),
+            KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE),
+        ]);
+        assert!(parse_keys("<Nope>").is_err());
+        assert_eq!(parse_keys("<>").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn round_trips()
+    {
+        let notation = "<C-a><S-Up><S-Tab><lt><CR><F12><A-x><C-A-Del>x y";
+        let keys = parse_keys(notation).unwrap();
+        assert_eq!(format_keys(&keys).unwrap(), notation);
+        assert_eq!(parse_keys(&format_keys(&keys).unwrap()).unwrap(), keys);
+    }
+
+    #[test]
+    fn refuses_unnamed_keys()
+    {
+        let keys = [KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE), KeyEvent::new(KeyCode::CapsLock, KeyModifiers::NONE)];
+        assert!(format_keys(&keys).is_err());
+    }
+}
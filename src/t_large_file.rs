@@ -0,0 +1,324 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+
+pub const LARGE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+pub const WINDOW_LINES: usize = 1024;
+pub const WINDOW_BYTES: usize = 1024 * 1024;
+const READ_SIZE: usize = 1024 * 1024;
+
+
+pub struct TLargeFile
+{
+    pub len: u64,
+    pub start: u64,
+    pub end: u64,
+    pub first_line: usize,
+    pub partial: bool,
+    pub capped: bool,
+    offsets: Arc<Mutex<Vec<u64>>>,
+    indexed: Arc<AtomicBool>,
+}
+
+
+impl TLargeFile
+{
+    pub fn build(file: &mut File, path: &str) -> std::io::Result<(TLargeFile, String)>
+    {
+        let len = file.metadata()?.len();
+        let offsets = Arc::new(Mutex::new(vec![0]));
+        let indexed = Arc::new(AtomicBool::new(false));
+
+        let thread_offsets = Arc::clone(&offsets);
+        let thread_indexed = Arc::clone(&indexed);
+        let thread_path = path.to_string();
+        thread::spawn(move || {
+            index_lines(thread_path.as_str(), &thread_offsets);
+            thread_indexed.store(true, Ordering::Release);
+        });
+
+        let mut t_large_file = TLargeFile { len, start: 0, end: 0, first_line: 0, partial: false, capped: false, offsets, indexed };
+        let content = t_large_file.load(file, 0, 0)?;
+
+        Ok((t_large_file, content))
+    }
+
+    pub fn is_indexed(&self) -> bool
+    {
+        self.indexed.load(Ordering::Acquire)
+    }
+
+    pub fn line_count(&self) -> usize
+    {
+        self.offsets.lock().unwrap().len()
+    }
+
+    pub fn line_offset(&self, line: usize) -> Option<u64>
+    {
+        self.offsets.lock().unwrap().get(line).copied()
+    }
+
+    pub fn seek_line(&self, file: &mut File, line: usize) -> std::io::Result<(usize, u64)>
+    {
+        let (mut current, mut offset) = {
+            let offsets = self.offsets.lock().unwrap();
+            let known = line.min(offsets.len() - 1);
+            (known, offsets[known])
+        };
+        if current == line || self.is_indexed()
+        {
+            return Ok((current, offset));
+        }
+
+        // the index has not got this far, so count on from the nearest line start we know
+        if !self.partial && self.first_line > current && self.first_line <= line
+        {
+            (current, offset) = (self.first_line, self.start);
+        }
+
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = vec![0; READ_SIZE];
+        let mut position = offset;
+        while current < line
+        {
+            let n = file.read(&mut buffer)?;
+            if n == 0
+            {
+                break;
+            }
+            for (i, b) in buffer[..n].iter().enumerate()
+            {
+                if *b == b'\n'
+                {
+                    current += 1;
+                    offset = position + i as u64 + 1;
+                    if current == line
+                    {
+                        break;
+                    }
+                }
+            }
+            position += n as u64;
+        }
+
+        Ok((current, offset))
+    }
+
+    pub fn count_lines(&self, file: &mut File, from: u64, to: u64) -> std::io::Result<usize>
+    {
+        file.seek(SeekFrom::Start(from))?;
+
+        let mut bytes = Vec::new();
+        file.take(to.saturating_sub(from)).read_to_end(&mut bytes)?;
+
+        Ok(bytes.iter().filter(|b| **b == b'\n').count())
+    }
+
+    pub fn load(&mut self, file: &mut File, first_line: usize, start: u64) -> std::io::Result<String>
+    {
+        let mut start = start.min(self.len);
+        let mut before = [0];
+        if start > 0
+        {
+            file.seek(SeekFrom::Start(start - 1))?;
+            file.read_exact(&mut before)?;
+        }
+        file.seek(SeekFrom::Start(start))?;
+
+        let mut bytes: Vec<u8> = Vec::new();
+        file.take(WINDOW_BYTES as u64).read_to_end(&mut bytes)?;
+
+        // a window started by byte offset may begin inside a character
+        let skip = bytes.iter().take(3).take_while(|b| **b & 0xc0 == 0x80).count();
+        bytes.drain(..skip);
+        start += skip as u64;
+
+        self.capped = false;
+        match bytes.iter().enumerate().filter(|(_, b)| **b == b'\n').nth(WINDOW_LINES - 1)
+        {
+            Some((i, _)) => { bytes.truncate(i + 1); },
+            None if start + bytes.len() as u64 >= self.len => {},
+            None => {
+                self.capped = true;
+                let tail = bytes.iter().rev().take(4).position(|b| *b & 0xc0 != 0x80);
+                if let Some(i) = tail
+                {
+                    let lead = bytes[bytes.len() - 1 - i];
+                    let width = match lead
+                    {
+                        0xf0.. => 4,
+                        0xe0.. => 3,
+                        0xc0.. => 2,
+                        _ => 1,
+                    };
+                    if width > i + 1
+                    {
+                        bytes.truncate(bytes.len() - 1 - i);
+                    }
+                }
+            },
+        }
+
+        self.first_line = first_line;
+        self.start = start;
+        self.end = start + bytes.len() as u64;
+        self.partial = start > 0 && before[0] != b'\n';
+
+        Ok(decode(&bytes))
+    }
+}
+
+
+// invalid bytes become one '?' each so indexes into the content stay byte offsets into the file
+fn decode(bytes: &[u8]) -> String
+{
+    let mut content = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks()
+    {
+        content.push_str(chunk.valid());
+        content.extend(chunk.invalid().iter().map(|_| '?'));
+    }
+
+    content
+}
+
+
+fn index_lines(path: &str, offsets: &Mutex<Vec<u64>>)
+{
+    let mut file = match File::open(path)
+    {
+        Ok(f) => f,
+        Err(_) => return,
+    };
+
+    let mut buffer = vec![0; READ_SIZE];
+    let mut offset: u64 = 0;
+    loop
+    {
+        let n = match file.read(&mut buffer)
+        {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+
+        let found: Vec<u64> = buffer[..n].iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .map(|(i, _)| offset + i as u64 + 1)
+            .collect();
+        offsets.lock().unwrap().extend(found);
+
+        offset += n as u64;
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::path::PathBuf;
+
+    struct TSource
+    {
+        path: PathBuf,
+        lines: Vec<String>,
+    }
+
+    impl TSource
+    {
+        fn build(name: &str, lines: Vec<String>) -> TSource
+        {
+            let path = std::env::temp_dir().join(format!("tim-large-{}-{name}", std::process::id()));
+            std::fs::write(&path, lines.iter().map(|l| format!("{l}\n")).collect::<String>()).unwrap();
+            TSource { path, lines }
+        }
+
+        fn open(&self) -> (File, TLargeFile, String)
+        {
+            let mut file = File::open(&self.path).unwrap();
+            let (large, content) = TLargeFile::build(&mut file, self.path.to_str().unwrap()).unwrap();
+            while !large.is_indexed()
+            {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+            (file, large, content)
+        }
+
+        fn window(&self, first_line: usize) -> String
+        {
+            let last = (first_line + WINDOW_LINES).min(self.lines.len());
+            self.lines[first_line..last].iter().map(|l| format!("{l}\n")).collect()
+        }
+    }
+
+    impl Drop for TSource
+    {
+        fn drop(&mut self)
+        {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn numbered(count: usize) -> Vec<String>
+    {
+        (0..count).map(|n| format!("line {n} {}", "x".repeat(n % 7))).collect()
+    }
+
+    #[test]
+    fn reloads_the_first_window_after_a_far_jump()
+    {
+        let source = TSource::build("jump", numbered(5000));
+        let (mut file, mut large, content) = source.open();
+        assert_eq!(content, source.window(0));
+
+        let (line, offset) = large.seek_line(&mut file, 3000).unwrap();
+        assert_eq!(large.load(&mut file, line, offset).unwrap(), source.window(3000));
+
+        assert_eq!(large.load(&mut file, 0, 0).unwrap(), source.window(0));
+        assert_eq!((large.first_line, large.start, large.partial), (0, 0, false));
+    }
+
+    #[test]
+    fn crosses_windows_forward_and_backward()
+    {
+        let source = TSource::build("cross", numbered(5000));
+        let (mut file, mut large, _) = source.open();
+
+        let mut first_line = 0;
+        while large.end < large.len
+        {
+            let (line, start) = (large.first_line + WINDOW_LINES, large.end);
+            assert_eq!(large.load(&mut file, line, start).unwrap(), source.window(line));
+            first_line = line;
+        }
+        assert_eq!(first_line, 4096);
+
+        while first_line > 0
+        {
+            first_line = first_line.saturating_sub(WINDOW_LINES / 2);
+            let (line, start) = large.seek_line(&mut file, first_line).unwrap();
+            assert_eq!(line, first_line);
+            assert_eq!(large.load(&mut file, line, start).unwrap(), source.window(first_line));
+        }
+    }
+
+    #[test]
+    fn starts_byte_windows_on_character_boundaries()
+    {
+        let source = TSource::build("bytes", vec!["é".repeat(WINDOW_BYTES), String::from("end")]);
+        let (mut file, mut large, content) = source.open();
+        assert!(large.capped);
+        assert_eq!(content.len(), WINDOW_BYTES);
+
+        let content = large.load(&mut file, 0, 3).unwrap();
+        assert_eq!((large.start, large.partial), (4, true));
+        assert!(content.starts_with('é'));
+
+        assert_eq!(large.load(&mut file, 0, (WINDOW_BYTES * 2 - 2) as u64).unwrap(), "é\nend\n");
+        assert_eq!(large.load(&mut file, 0, (WINDOW_BYTES * 2 - 1) as u64).unwrap(), "\nend\n");
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::t_dirs::data_dir;
+
+
+fn key(path: &str) -> Option<String>
+{
+    let key = Path::new(path).canonicalize().ok()?.to_string_lossy().into_owned();
+
+    (!key.contains(['\t', '\n'])).then_some(key)
+}
+
+
+pub fn load(path: &str) -> BTreeMap<char, (usize, usize)>
+{
+    let mut marks = BTreeMap::new();
+
+    let (Some(key), Some(dir)) = (key(path), data_dir()) else { return marks };
+    let Ok(saved) = fs::read_to_string(dir.join("marks")) else { return marks };
+
+    for line in saved.lines()
+    {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if let [file, name, line, column] = fields[..]
+        {
+            if let (true, Some(name), Ok(line), Ok(column)) = (file == key, name.chars().next(), line.parse(), column.parse())
+            {
+                marks.insert(name, (line, column));
+            }
+        }
+    }
+
+    marks
+}
+
+
+pub fn store(path: &str, marks: &BTreeMap<char, (usize, usize)>) -> std::io::Result<()>
+{
+    let (Some(key), Some(dir)) = (key(path), data_dir()) else { return Ok(()) };
+    let file = dir.join("marks");
+
+    let saved = fs::read_to_string(&file).unwrap_or_default();
+    let mut lines: Vec<String> = saved.lines()
+        .filter(|line| line.split('\t').next() != Some(key.as_str()))
+        .map(String::from)
+        .collect();
+    lines.extend(marks.iter().map(|(name, (line, column))| format!("{key}\t{name}\t{line}\t{column}")));
+
+    fs::create_dir_all(&dir)?;
+    fs::write(file, lines.iter().map(|line| format!("{line}\n")).collect::<String>())
+}
@@ -0,0 +1,33 @@
+use crossterm::cursor::SetCursorStyle;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TMode
+{
+    Normal,
+    Insert,
+    Visual,
+}
+
+
+impl TMode
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            TMode::Normal => "NORMAL",
+            TMode::Insert => "INSERT",
+            TMode::Visual => "VISUAL",
+        }
+    }
+
+    pub fn cursor_style(&self) -> SetCursorStyle
+    {
+        match self
+        {
+            TMode::Insert => SetCursorStyle::SteadyBar,
+            _ => SetCursorStyle::SteadyBlock,
+        }
+    }
+}
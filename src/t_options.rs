@@ -0,0 +1,196 @@
+use crate::t_theme::TColors;
+
+
+pub struct TOptions
+{
+    pub view: bool,
+    pub tab_bar: bool,
+    pub modal: bool,
+    pub numbers: TNumbers,
+    pub theme: String,
+    pub colors: Option<TColors>,
+    pub tab_width: usize,
+    pub soft_tabs: bool,
+    pub detect_indent: bool,
+    pub syntax: bool,
+    pub auto_indent: bool,
+    pub wrap: bool,
+    pub word_wrap: bool,
+    pub wrap_indent: bool,
+    pub wrap_marker: String,
+    pub visual_rows: bool,
+    pub autosave: u64,
+    pub undo_limit: usize,
+    pub explorer: TExplorerOptions,
+}
+
+
+impl Default for TOptions
+{
+    fn default() -> TOptions
+    {
+        TOptions {
+            view: false,
+            tab_bar: false,
+            modal: false,
+            numbers: TNumbers::Off,
+            theme: String::from("default"),
+            colors: None,
+            tab_width: 4,
+            soft_tabs: true,
+            detect_indent: true,
+            syntax: true,
+            auto_indent: true,
+            wrap: true,
+            word_wrap: true,
+            wrap_indent: false,
+            wrap_marker: String::from("↪ "),
+            visual_rows: true,
+            autosave: 0,
+            undo_limit: 10,
+            explorer: TExplorerOptions::default(),
+        }
+    }
+}
+
+
+impl TOptions
+{
+    pub const NAMES: [&'static str; 12] = [
+        "autoindent", "modal", "numbers", "softtabs", "syntax", "tabs", "tabwidth", "theme", "visualrows", "wordwrap", "wrap", "wrapindent",
+    ];
+
+    pub fn indent(&self) -> String
+    {
+        if self.soft_tabs { " ".repeat(self.tab_width) } else { String::from("\t") }
+    }
+
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), &'static str>
+    {
+        let (name, enable) = match name.strip_prefix("no")
+        {
+            Some(n) if value.is_none() && TOptions::NAMES.contains(&n) => (n, false),
+            _ => (name, true),
+        };
+
+        match (name, value)
+        {
+            ("numbers", None) => {
+                self.numbers = if enable { TNumbers::Absolute } else { TNumbers::Off };
+            },
+            ("numbers", Some(mode)) => {
+                self.numbers = TNumbers::parse(mode).ok_or("Invalid value for numbers.")?;
+            },
+            ("modal", None) => { self.modal = enable; },
+            ("modal", Some(value)) => { self.modal = parse_bool(value).ok_or("Invalid value for modal.")?; },
+            ("tabs", None) => { self.tab_bar = enable; },
+            ("tabs", Some(value)) => { self.tab_bar = parse_bool(value).ok_or("Invalid value for tabs.")?; },
+            ("softtabs", None) => { self.soft_tabs = enable; },
+            ("softtabs", Some(value)) => { self.soft_tabs = parse_bool(value).ok_or("Invalid value for softtabs.")?; },
+            ("autoindent", None) => { self.auto_indent = enable; },
+            ("autoindent", Some(value)) => { self.auto_indent = parse_bool(value).ok_or("Invalid value for autoindent.")?; },
+            ("syntax", None) => { self.syntax = enable; },
+            ("syntax", Some(value)) => { self.syntax = parse_bool(value).ok_or("Invalid value for syntax.")?; },
+            ("wrap", None) => { self.wrap = enable; },
+            ("wrap", Some(value)) => { self.wrap = parse_bool(value).ok_or("Invalid value for wrap.")?; },
+            ("wordwrap", None) => { self.word_wrap = enable; },
+            ("wordwrap", Some(value)) => { self.word_wrap = parse_bool(value).ok_or("Invalid value for wordwrap.")?; },
+            ("wrapindent", None) => { self.wrap_indent = enable; },
+            ("wrapindent", Some(value)) => { self.wrap_indent = parse_bool(value).ok_or("Invalid value for wrapindent.")?; },
+            ("visualrows", None) => { self.visual_rows = enable; },
+            ("visualrows", Some(value)) => { self.visual_rows = parse_bool(value).ok_or("Invalid value for visualrows.")?; },
+            ("tabwidth", None) => return Err("tabwidth needs a value."),
+            ("theme", None) => return Err("theme needs a value."),
+            ("theme", Some(name)) => { self.theme = name.to_string(); },
+            ("tabwidth", Some(value)) => {
+                self.tab_width = value.parse().ok().filter(|w| (1..=16).contains(w)).ok_or("Invalid value for tabwidth.")?;
+            },
+            _ => return Err("Unknown option."),
+        }
+
+        Ok(())
+    }
+
+    pub fn show(&self) -> String
+    {
+        let modal = if self.modal { "modal" } else { "nomodal" };
+        let tabs = if self.tab_bar { "tabs" } else { "notabs" };
+        let soft_tabs = if self.soft_tabs { "softtabs" } else { "nosofttabs" };
+        let syntax = if self.syntax { "syntax" } else { "nosyntax" };
+        let auto_indent = if self.auto_indent { "autoindent" } else { "noautoindent" };
+        let visual_rows = if self.visual_rows { "visualrows" } else { "novisualrows" };
+        let word_wrap = if self.word_wrap { "wordwrap" } else { "nowordwrap" };
+        let wrap = if self.wrap { "wrap" } else { "nowrap" };
+        let wrap_indent = if self.wrap_indent { "wrapindent" } else { "nowrapindent" };
+        format!(
+            "{auto_indent} {modal} numbers={} {soft_tabs} {syntax} {tabs} tabwidth={} theme={} {visual_rows} {word_wrap} {wrap} {wrap_indent}",
+            self.numbers.name(), self.tab_width, self.theme,
+        )
+    }
+}
+
+
+pub struct TExplorerOptions
+{
+    pub directory: String,
+    pub hidden: bool,
+    pub dirs_first: bool,
+}
+
+
+impl Default for TExplorerOptions
+{
+    fn default() -> TExplorerOptions
+    {
+        TExplorerOptions { directory: String::from("./"), hidden: true, dirs_first: false }
+    }
+}
+
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum TNumbers
+{
+    #[default]
+    Off,
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+
+impl TNumbers
+{
+    pub fn parse(mode: &str) -> Option<TNumbers>
+    {
+        match mode
+        {
+            "off" => Some(TNumbers::Off),
+            "absolute" => Some(TNumbers::Absolute),
+            "relative" => Some(TNumbers::Relative),
+            "hybrid" => Some(TNumbers::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            TNumbers::Off => "off",
+            TNumbers::Absolute => "absolute",
+            TNumbers::Relative => "relative",
+            TNumbers::Hybrid => "hybrid",
+        }
+    }
+}
+
+
+fn parse_bool(value: &str) -> Option<bool>
+{
+    match value
+    {
+        "true" | "on" | "yes" => Some(true),
+        "false" | "off" | "no" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs::File;
+use std::io::{stdout, IsTerminal, Write};
+use std::sync::OnceLock;
+
+
+#[cfg(windows)]
+const TTY_PATH: &str = "CONOUT$";
+#[cfg(not(windows))]
+const TTY_PATH: &str = "/dev/tty";
+
+static TTY: OnceLock<Option<File>> = OnceLock::new();
+
+
+pub fn screen() -> Box<dyn Write>
+{
+    let tty = TTY.get_or_init(|| {
+        if stdout().is_terminal()
+        {
+            None
+        }
+        else
+        {
+            File::options().write(true).open(TTY_PATH).ok()
+        }
+    });
+
+    match tty
+    {
+        Some(f) => Box::new(f),
+        None => Box::new(stdout()),
+    }
+}
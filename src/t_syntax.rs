@@ -0,0 +1,925 @@
+use std::collections::HashSet;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::t_config::{entries, TValue};
+use crate::t_dirs::config_dir;
+
+
+const BUILT_IN: [(&str, &str); 7] = [
+    ("rust", include_str!("../languages/rust.toml")),
+    ("toml", include_str!("../languages/toml.toml")),
+    ("markdown", include_str!("../languages/markdown.toml")),
+    ("json", include_str!("../languages/json.toml")),
+    ("yaml", include_str!("../languages/yaml.toml")),
+    ("shell", include_str!("../languages/shell.toml")),
+    ("python", include_str!("../languages/python.toml")),
+];
+
+static LANGUAGES: OnceLock<Vec<TLanguage>> = OnceLock::new();
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TScope
+{
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Type,
+    Function,
+    Constant,
+    Operator,
+    Property,
+    Heading,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TState
+{
+    Normal,
+    Comment(usize),
+    String(char),
+    LongString(usize),
+    RawString(usize),
+}
+
+
+pub struct TLanguage
+{
+    pub name: String,
+    extensions: Vec<String>,
+    files: Vec<String>,
+    shebangs: Vec<String>,
+    line_comments: Vec<String>,
+    block_comment: Option<(String, String)>,
+    nested_comments: bool,
+    strings: Vec<char>,
+    multiline_strings: bool,
+    long_strings: Vec<String>,
+    raw_strings: bool,
+    char_literals: bool,
+    string_prefixes: Vec<String>,
+    keywords: HashSet<String>,
+    types: HashSet<String>,
+    constants: HashSet<String>,
+    capitalized_types: bool,
+    functions: bool,
+    macros: bool,
+    numbers: bool,
+    operators: Vec<char>,
+    keys: Option<char>,
+    tables: bool,
+    headings: Option<char>,
+    variables: Option<char>,
+    decorators: Option<char>,
+    indent_after: Vec<String>,
+}
+
+
+impl TLanguage
+{
+    fn build(stem: &str, content: &str) -> Result<TLanguage, String>
+    {
+        let mut t_language = TLanguage { name: stem.to_string(),
+            extensions: Vec::new(),
+            files: Vec::new(),
+            shebangs: Vec::new(),
+            line_comments: Vec::new(),
+            block_comment: None,
+            nested_comments: false,
+            strings: Vec::new(),
+            multiline_strings: false,
+            long_strings: Vec::new(),
+            raw_strings: false,
+            char_literals: false,
+            string_prefixes: Vec::new(),
+            keywords: HashSet::new(),
+            types: HashSet::new(),
+            constants: HashSet::new(),
+            capitalized_types: false,
+            functions: false,
+            macros: false,
+            numbers: true,
+            operators: Vec::new(),
+            keys: None,
+            tables: false,
+            headings: None,
+            variables: None,
+            decorators: None,
+            indent_after: Vec::new(),
+        };
+
+        for (line, entry) in entries(content)
+        {
+            entry.and_then(|(key, value)| t_language.set(key.as_str(), value))
+                .map_err(|err| format!("Language {stem} line {line}: {err}"))?;
+        }
+
+        Ok(t_language)
+    }
+
+    fn set(&mut self, key: &str, value: TValue) -> Result<(), &'static str>
+    {
+        let words = |value: &str| value.split_whitespace().map(String::from).collect::<Vec<String>>();
+        let char = |value: &str| {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next())
+            {
+                (Some(c), None) => Ok(c),
+                _ => Err("Expected a single character."),
+            }
+        };
+
+        match (key, value)
+        {
+            ("name", TValue::String(name)) => { self.name = name; },
+            ("extensions", TValue::String(list)) => { self.extensions = words(list.as_str()); },
+            ("files", TValue::String(list)) => { self.files = words(list.as_str()); },
+            ("shebangs", TValue::String(list)) => { self.shebangs = words(list.as_str()); },
+            ("line_comment", TValue::String(list)) => { self.line_comments = words(list.as_str()); },
+            ("block_comment", TValue::String(pair)) => {
+                let [open, close] = words(pair.as_str()).try_into().map_err(|_| "Expected an opening and a closing delimiter.")?;
+                self.block_comment = Some((open, close));
+            },
+            ("nested_comments", TValue::Boolean(nested)) => { self.nested_comments = nested; },
+            ("strings", TValue::String(list)) => {
+                self.strings = words(list.as_str()).iter().map(|w| char(w)).collect::<Result<_, _>>()?;
+            },
+            ("multiline_strings", TValue::Boolean(multiline)) => { self.multiline_strings = multiline; },
+            ("long_strings", TValue::String(list)) => { self.long_strings = words(list.as_str()); },
+            ("raw_strings", TValue::Boolean(raw)) => { self.raw_strings = raw; },
+            ("char_literals", TValue::Boolean(chars)) => { self.char_literals = chars; },
+            ("string_prefixes", TValue::String(list)) => { self.string_prefixes = words(list.as_str()); },
+            ("keywords", TValue::String(list)) => { self.keywords = words(list.as_str()).into_iter().collect(); },
+            ("types", TValue::String(list)) => { self.types = words(list.as_str()).into_iter().collect(); },
+            ("constants", TValue::String(list)) => { self.constants = words(list.as_str()).into_iter().collect(); },
+            ("capitalized_types", TValue::Boolean(capitalized)) => { self.capitalized_types = capitalized; },
+            ("functions", TValue::Boolean(functions)) => { self.functions = functions; },
+            ("macros", TValue::Boolean(macros)) => { self.macros = macros; },
+            ("numbers", TValue::Boolean(numbers)) => { self.numbers = numbers; },
+            ("operators", TValue::String(operators)) => { self.operators = operators.chars().filter(|c| !c.is_whitespace()).collect(); },
+            ("keys", TValue::String(key)) => { self.keys = Some(char(key.as_str())?); },
+            ("tables", TValue::Boolean(tables)) => { self.tables = tables; },
+            ("headings", TValue::String(heading)) => { self.headings = Some(char(heading.as_str())?); },
+            ("variables", TValue::String(variable)) => { self.variables = Some(char(variable.as_str())?); },
+            ("decorators", TValue::String(decorator)) => { self.decorators = Some(char(decorator.as_str())?); },
+            ("indent_after", TValue::String(list)) => { self.indent_after = words(list.as_str()); },
+
+            ("name" | "extensions" | "files" | "shebangs" | "line_comment" | "block_comment" | "strings" | "long_strings"
+                | "string_prefixes" | "keywords" | "types" | "constants" | "operators" | "keys" | "headings" | "variables" | "decorators"
+                | "indent_after", _) => {
+                return Err("Expected a string.");
+            },
+            ("nested_comments" | "multiline_strings" | "raw_strings" | "char_literals" | "capitalized_types" | "functions" | "macros"
+                | "numbers" | "tables", _) => {
+                return Err("Expected true or false.");
+            },
+            _ => return Err("Unknown setting."),
+        }
+
+        Ok(())
+    }
+
+    fn matches(&self, path: &str, content: &str) -> bool
+    {
+        let path = Path::new(path);
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let extension = path.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
+
+        if self.files.iter().any(|f| *f == name) || (!extension.is_empty() && self.extensions.iter().any(|e| *e == extension))
+        {
+            return true;
+        }
+
+        let shebang = match content.lines().next().and_then(|l| l.strip_prefix("#!"))
+        {
+            None => return false,
+            Some(shebang) => shebang,
+        };
+        let mut words = shebang.split_whitespace();
+        let interpreter = match words.next().map(|w| w.rsplit('/').next().unwrap())
+        {
+            Some("env") => words.find(|w| !w.starts_with('-')).unwrap_or_default(),
+            Some(interpreter) => interpreter,
+            None => return false,
+        };
+
+        self.shebangs.iter().any(|s| *s == interpreter)
+    }
+
+    pub fn tokenize(&self, line: &str, state: TState) -> (Vec<(usize, usize, TScope)>, TState)
+    {
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        let end = match state
+        {
+            TState::Normal => 0,
+            TState::Comment(depth) => match self.close_comment(line, 0, depth)
+            {
+                (end, TState::Normal) => end,
+                (_, state) => return (vec![(0, line.len(), TScope::Comment)], state),
+            },
+            TState::String(quote) => match close_string(line, 0, quote)
+            {
+                Some(end) => end,
+                None => return (vec![(0, line.len(), TScope::String)], state),
+            },
+            TState::LongString(n) => match line.find(self.long_strings[n].as_str())
+            {
+                Some(p) => p + self.long_strings[n].len(),
+                None => return (vec![(0, line.len(), TScope::String)], state),
+            },
+            TState::RawString(hashes) => match line.find(format!("\"{}", "#".repeat(hashes)).as_str())
+            {
+                Some(p) => p + 1 + hashes,
+                None => return (vec![(0, line.len(), TScope::String)], state),
+            },
+        };
+        if end > 0
+        {
+            let scope = if matches!(state, TState::Comment(_)) { TScope::Comment } else { TScope::String };
+            tokens.push((0, end, scope));
+            i = end;
+        }
+
+        let indent = line.len() - line.trim_start().len();
+        if i == 0
+        {
+            let trimmed = line.trim_start();
+            if self.headings.is_some_and(|h| trimmed.starts_with(h))
+            {
+                return (vec![(indent, line.len(), TScope::Heading)], TState::Normal);
+            }
+            if self.tables && trimmed.starts_with('[')
+            {
+                i = indent + trimmed.rfind(']').map_or(trimmed.len(), |e| e + 1);
+                tokens.push((indent, i, TScope::Heading));
+            }
+        }
+
+        let mut key = true;
+        let mut prefix = None;
+        while i < line.len()
+        {
+            let rest = &line[i..];
+            let c = rest.chars().next().unwrap();
+            let start = prefix.take().unwrap_or(i);
+
+            if c.is_whitespace()
+            {
+                i += c.len_utf8();
+                continue;
+            }
+
+            if self.line_comments.iter().any(|p| rest.starts_with(p.as_str())) && !is_word(line[..i].chars().next_back())
+            {
+                tokens.push((i, line.len(), TScope::Comment));
+                return (tokens, TState::Normal);
+            }
+
+            if let Some((open, _)) = self.block_comment.as_ref().filter(|(open, _)| rest.starts_with(open.as_str()))
+            {
+                let (end, state) = self.close_comment(line, i + open.len(), 1);
+                tokens.push((i, end, TScope::Comment));
+                if state != TState::Normal
+                {
+                    return (tokens, state);
+                }
+                i = end;
+                continue;
+            }
+
+            if let Some(n) = self.long_strings.iter().position(|d| rest.starts_with(d.as_str()))
+            {
+                let delimiter = self.long_strings[n].as_str();
+                match rest[delimiter.len()..].find(delimiter)
+                {
+                    Some(p) => {
+                        i += delimiter.len() * 2 + p;
+                        tokens.push((start, i, TScope::String));
+                    },
+                    None => {
+                        tokens.push((start, line.len(), TScope::String));
+                        return (tokens, TState::LongString(n));
+                    },
+                }
+                key = false;
+                continue;
+            }
+
+            if self.raw_strings && c == 'r' && !is_word(line[..i].chars().next_back())
+            {
+                let hashes = rest[1..].bytes().take_while(|b| *b == b'#').count();
+                if rest[1 + hashes..].starts_with('"')
+                {
+                    let close = format!("\"{}", "#".repeat(hashes));
+                    match rest[2 + hashes..].find(close.as_str())
+                    {
+                        Some(p) => {
+                            i += 2 + hashes + p + close.len();
+                            tokens.push((start, i, TScope::String));
+                        },
+                        None => {
+                            tokens.push((start, line.len(), TScope::String));
+                            return (tokens, TState::RawString(hashes));
+                        },
+                    }
+                    continue;
+                }
+            }
+
+            if c == '\'' && self.char_literals
+            {
+                match char_literal(rest)
+                {
+                    Some(n) => { tokens.push((start, i + n, TScope::String)); i += n; },
+                    None => { i += 1; },
+                }
+                continue;
+            }
+
+            if self.strings.contains(&c) && (start < i || !is_word(line[..i].chars().next_back()))
+            {
+                match close_string(line, i + 1, c)
+                {
+                    Some(end) => {
+                        let scope = if self.key_follows(&line[end..], false) { TScope::Property } else { TScope::String };
+                        tokens.push((start, end, scope));
+                        i = end;
+                        key = false;
+                    },
+                    None => {
+                        tokens.push((start, line.len(), TScope::String));
+                        return (tokens, if self.multiline_strings { TState::String(c) } else { TState::Normal });
+                    },
+                }
+                continue;
+            }
+
+            if self.variables == Some(c)
+            {
+                let n = variable_len(rest);
+                if n > 1
+                {
+                    tokens.push((i, i + n, TScope::Constant));
+                }
+                i += n;
+                continue;
+            }
+
+            if self.decorators == Some(c) && !is_word(line[..i].chars().next_back())
+            {
+                let n = 1 + word_len(&rest[1..]);
+                tokens.push((i, i + n, TScope::Function));
+                i += n;
+                continue;
+            }
+
+            if key && self.keys.is_some()
+            {
+                let n = rest.find(|c: char| !(is_word(Some(c)) || c == '-' || c == '.')).unwrap_or(rest.len());
+                if n > 0 && self.key_follows(&rest[n..], true)
+                {
+                    tokens.push((i, i + n, TScope::Property));
+                    i += n;
+                    key = false;
+                    continue;
+                }
+            }
+
+            if c.is_ascii_digit()
+            {
+                let n = number_len(rest);
+                if self.numbers && !is_word(line[..i].chars().next_back())
+                {
+                    tokens.push((i, i + n, TScope::Number));
+                }
+                i += n;
+                key = false;
+                continue;
+            }
+
+            if is_word(Some(c))
+            {
+                let n = word_len(rest);
+                let word = &rest[..n];
+                let after = &rest[n..];
+
+                if self.string_prefixes.iter().any(|p| p == word) && after.starts_with(|q: char| self.strings.contains(&q))
+                {
+                    prefix = Some(i);
+                    i += n;
+                    continue;
+                }
+
+                let scope = if self.keywords.contains(word)
+                {
+                    Some(TScope::Keyword)
+                }
+                else if self.constants.contains(word)
+                {
+                    Some(TScope::Constant)
+                }
+                else if self.types.contains(word) || (self.capitalized_types && word.starts_with(|c: char| c.is_uppercase()))
+                {
+                    Some(TScope::Type)
+                }
+                else if self.macros && after.starts_with('!') && !after.starts_with("!=")
+                {
+                    tokens.push((i, i + n + 1, TScope::Function));
+                    i += n + 1;
+                    key = false;
+                    continue;
+                }
+                else if self.functions && after.trim_start().starts_with('(')
+                {
+                    Some(TScope::Function)
+                }
+                else
+                {
+                    None
+                };
+
+                if let Some(scope) = scope
+                {
+                    tokens.push((i, i + n, scope));
+                }
+                i += n;
+                key = false;
+                continue;
+            }
+
+            if self.operators.contains(&c)
+            {
+                tokens.push((i, i + c.len_utf8(), TScope::Operator));
+            }
+            if !(c == '-' && i == indent)
+            {
+                key = false;
+            }
+            i += c.len_utf8();
+        }
+
+        (tokens, TState::Normal)
+    }
+
+    pub fn indents(&self, line: &str) -> bool
+    {
+        let line = line.trim_end();
+        self.indent_after.iter().any(|word| {
+            line.strip_suffix(word.as_str()).is_some_and(|rest| !word.starts_with(|c: char| is_word(Some(c))) || !is_word(rest.chars().next_back()))
+        })
+    }
+
+    pub fn dedents(&self, c: char) -> bool
+    {
+        const PAIRS: [(&str, char); 3] = [("(", ')'), ("[", ']'), ("{", '}')];
+
+        PAIRS.iter().any(|(open, close)| *close == c && self.indent_after.iter().any(|w| w == open))
+    }
+
+    fn close_comment(&self, line: &str, from: usize, depth: usize) -> (usize, TState)
+    {
+        let (open, close) = self.block_comment.as_ref().unwrap();
+        let mut depth = depth;
+        let mut i = from;
+
+        while i < line.len()
+        {
+            let rest = &line[i..];
+            if rest.starts_with(close.as_str())
+            {
+                i += close.len();
+                depth -= 1;
+                if depth == 0
+                {
+                    return (i, TState::Normal);
+                }
+            }
+            else if self.nested_comments && rest.starts_with(open.as_str())
+            {
+                i += open.len();
+                depth += 1;
+            }
+            else
+            {
+                i += rest.chars().next().unwrap().len_utf8();
+            }
+        }
+
+        (line.len(), TState::Comment(depth))
+    }
+
+    fn key_follows(&self, after: &str, strict: bool) -> bool
+    {
+        let key = match self.keys
+        {
+            None => return false,
+            Some(key) => key,
+        };
+
+        match after.trim_start().strip_prefix(key)
+        {
+            None => false,
+            Some(rest) => !strict || key != ':' || rest.is_empty() || rest.starts_with(char::is_whitespace),
+        }
+    }
+}
+
+
+pub struct TSyntax
+{
+    pub language: &'static TLanguage,
+    states: Vec<TState>,
+}
+
+
+impl TSyntax
+{
+    pub fn detect(path: &str, content: &str) -> Option<TSyntax>
+    {
+        let language = languages().iter().find(|l| l.matches(path, content))?;
+
+        Some(TSyntax { language, states: vec![TState::Normal] })
+    }
+
+    pub fn invalidate(&mut self, line: usize)
+    {
+        self.states.truncate(line + 1);
+    }
+
+    pub fn highlight(&mut self, content: &str, lines: Range<usize>) -> Vec<([usize; 2], TScope)>
+    {
+        let mut tokens = Vec::new();
+        let mut offset = 0;
+
+        for (number, line) in content.split('\n').enumerate()
+        {
+            if number >= lines.end
+            {
+                break;
+            }
+
+            if number >= lines.start || number + 1 >= self.states.len()
+            {
+                let (line_tokens, state) = self.language.tokenize(line, self.states[number]);
+                if number + 1 < self.states.len()
+                {
+                    self.states[number + 1] = state;
+                }
+                else
+                {
+                    self.states.push(state);
+                }
+
+                if number >= lines.start
+                {
+                    tokens.extend(line_tokens.into_iter().map(|(s, e, scope)| ([offset + s, offset + e], scope)));
+                }
+            }
+
+            offset += line.len() + 1;
+        }
+
+        tokens
+    }
+
+    pub fn literals(&mut self, content: &str, lines: Range<usize>) -> Vec<([usize; 2], TScope)>
+    {
+        let mut literals: Vec<([usize; 2], TScope)> = Vec::new();
+        for ([start, end], scope) in self.highlight(content, lines)
+        {
+            if !matches!(scope, TScope::String | TScope::Comment)
+            {
+                continue;
+            }
+
+            match literals.last_mut()
+            {
+                Some(([_, last], last_scope)) if *last_scope == scope && *last + 1 == start && content.as_bytes()[*last] == b'\n' => { *last = end; },
+                _ => literals.push(([start, end], scope)),
+            }
+        }
+
+        literals
+    }
+}
+
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+
+pub struct TPairs
+{
+    pub pairs: Vec<[usize; 2]>,
+    pub unmatched: Vec<usize>,
+    pub open: Vec<(char, usize)>,
+}
+
+
+#[derive(Default)]
+pub struct TBrackets
+{
+    stacks: Vec<Vec<(char, usize)>>,
+    partner: Option<(usize, Option<usize>)>,
+}
+
+
+impl TBrackets
+{
+    pub fn invalidate(&mut self, line: usize)
+    {
+        self.stacks.truncate(line + 1);
+        self.partner = None;
+    }
+
+    // pairs brackets from the start of line `from` up to `to`, carrying on from the brackets left open before that line
+    pub fn pair(&mut self, content: &str, mut syntax: Option<&mut TSyntax>, from: usize, to: usize) -> TPairs
+    {
+        if self.stacks.is_empty()
+        {
+            self.stacks.push(Vec::new());
+        }
+
+        let first = from.min(self.stacks.len() - 1);
+        let begin = line_start(content, 0, first);
+        let start = line_start(content, begin, from - first);
+        let last = first + content[begin..to].matches('\n').count();
+        let literals = syntax.as_mut().map_or(Vec::new(), |syntax| syntax.literals(content, first..last + 1));
+
+        let mut line = first;
+        let mut pairs = Vec::new();
+        let mut unmatched = Vec::new();
+        let mut open = self.stacks[first].clone();
+        let mut literals = literals.iter().peekable();
+
+        for (i, c) in content[begin..to].char_indices().map(|(i, c)| (begin + i, c))
+        {
+            if c == '\n'
+            {
+                line += 1;
+                if line == self.stacks.len()
+                {
+                    self.stacks.push(open.clone());
+                }
+                continue;
+            }
+
+            while literals.next_if(|([_, end], _)| *end <= i).is_some() {}
+            if literals.peek().is_some_and(|([start, _], _)| *start <= i)
+            {
+                continue;
+            }
+
+            if PAIRS.iter().any(|(o, _)| *o == c)
+            {
+                open.push((c, i));
+            }
+            else if let Some((o, _)) = PAIRS.iter().find(|(_, e)| *e == c)
+            {
+                match open.iter().rposition(|(c, _)| c == o)
+                {
+                    Some(n) => {
+                        unmatched.extend(open.drain(n + 1..).map(|(_, i)| i));
+                        pairs.push([open.pop().unwrap().1, i]);
+                    },
+                    None => unmatched.push(i),
+                }
+            }
+        }
+
+        pairs.retain(|[_, close]| *close >= start);
+        unmatched.retain(|i| *i >= start);
+        unmatched.sort();
+
+        TPairs { pairs, unmatched, open }
+    }
+
+    pub fn partner(&mut self, content: &str, syntax: Option<&mut TSyntax>, index: usize) -> Option<usize>
+    {
+        if let Some((_, partner)) = self.partner.filter(|(i, _)| *i == index)
+        {
+            return partner;
+        }
+
+        let partner = self.find_partner(content, syntax, index);
+        self.partner = Some((index, partner));
+
+        partner
+    }
+
+    // the pair of `open` brackets around index, or the pair whose bracket is at index
+    pub fn enclosing(&mut self, content: &str, mut syntax: Option<&mut TSyntax>, index: usize, open: char) -> Option<[usize; 2]>
+    {
+        let close = PAIRS.iter().find(|(o, _)| *o == open)?.1;
+        if content[index..].starts_with([open, close])
+        {
+            if let Some(partner) = self.partner(content, syntax.as_deref_mut(), index)
+            {
+                return Some([index.min(partner), index.max(partner)]);
+            }
+        }
+
+        let line = content[..index].matches('\n').count();
+        let first = self.pair(content, syntax.as_deref_mut(), line, index).open.iter().rev().find(|(c, _)| *c == open)?.1;
+        let last = self.partner(content, syntax, first)?;
+
+        Some([first, last])
+    }
+
+    // scan more and more lines below the bracket until it is closed
+    fn find_partner(&mut self, content: &str, mut syntax: Option<&mut TSyntax>, index: usize) -> Option<usize>
+    {
+        let c = content[index..].chars().next()?;
+        if !PAIRS.iter().any(|(o, e)| *o == c || *e == c)
+        {
+            return None;
+        }
+
+        let line = content[..index].matches('\n').count();
+        let mut end = content[index..].find('\n').map_or(content.len(), |i| index + i);
+        let mut span = 1;
+        loop
+        {
+            let found = self.pair(content, syntax.as_deref_mut(), line, end);
+            if let Some([open, close]) = found.pairs.iter().find(|[open, close]| *open == index || *close == index)
+            {
+                return Some(if *open == index { *close } else { *open });
+            }
+            if end == content.len() || !found.open.iter().any(|(_, i)| *i == index)
+            {
+                return None;
+            }
+
+            end = content[end + 1..].match_indices('\n').nth(span - 1).map_or(content.len(), |(i, _)| end + 1 + i);
+            span *= 2;
+        }
+    }
+}
+
+
+fn line_start(content: &str, from: usize, lines: usize) -> usize
+{
+    match lines
+    {
+        0 => from,
+        _ => content[from..].match_indices('\n').nth(lines - 1).map_or(content.len(), |(i, _)| from + i + 1),
+    }
+}
+
+
+pub fn load_languages() -> Option<String>
+{
+    let mut languages = Vec::new();
+    let mut error = None;
+
+    let mut user = Vec::new();
+    if let Some(Ok(dir)) = config_dir().map(|dir| fs::read_dir(dir.join("languages")))
+    {
+        for entry in dir.flatten()
+        {
+            let path = entry.path();
+            match (path.file_stem(), path.extension(), fs::read_to_string(&path))
+            {
+                (Some(stem), Some(extension), Ok(content)) if extension == "toml" => user.push((stem.to_string_lossy().into_owned(), content)),
+                _ => {},
+            }
+        }
+    }
+    user.sort();
+
+    for (stem, content) in &user
+    {
+        match TLanguage::build(stem, content)
+        {
+            Ok(language) => languages.push(language),
+            Err(err) => { error.get_or_insert(err); },
+        }
+    }
+
+    for (stem, content) in BUILT_IN
+    {
+        if !user.iter().any(|(s, _)| s == stem)
+        {
+            languages.push(TLanguage::build(stem, content).unwrap());
+        }
+    }
+
+    let _ = LANGUAGES.set(languages);
+
+    error
+}
+
+
+fn languages() -> &'static Vec<TLanguage>
+{
+    LANGUAGES.get_or_init(|| BUILT_IN.iter().map(|(stem, content)| TLanguage::build(stem, content).unwrap()).collect())
+}
+
+
+fn is_word(c: Option<char>) -> bool
+{
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+
+fn word_len(string: &str) -> usize
+{
+    string.find(|c: char| !is_word(Some(c))).unwrap_or(string.len())
+}
+
+
+fn number_len(string: &str) -> usize
+{
+    let bytes = string.as_bytes();
+    let mut n = 0;
+    while n < bytes.len() && (bytes[n].is_ascii_alphanumeric() || bytes[n] == b'_' || (bytes[n] == b'.' && bytes.get(n + 1).is_some_and(u8::is_ascii_digit)))
+    {
+        n += 1;
+    }
+    n
+}
+
+
+fn variable_len(string: &str) -> usize
+{
+    let rest = &string[1..];
+    if rest.starts_with('{')
+    {
+        return 1 + rest.find('}').map_or(rest.len(), |e| e + 1);
+    }
+
+    match word_len(rest)
+    {
+        0 if rest.starts_with(|c: char| "#?@*$!-".contains(c)) => 2,
+        n => 1 + n,
+    }
+}
+
+
+fn close_string(line: &str, from: usize, quote: char) -> Option<usize>
+{
+    let mut chars = line[from..].char_indices();
+    while let Some((i, c)) = chars.next()
+    {
+        if c == '\\'
+        {
+            chars.next();
+        }
+        else if c == quote
+        {
+            return Some(from + i + 1);
+        }
+    }
+
+    None
+}
+
+
+fn char_literal(string: &str) -> Option<usize>
+{
+    let rest = &string[1..];
+    if rest.starts_with('\\')
+    {
+        return rest.get(2..)?.find('\'').filter(|p| *p < 10).map(|p| p + 4);
+    }
+
+    let c = rest.chars().next()?;
+    rest[c.len_utf8()..].starts_with('\'').then(|| c.len_utf8() + 2)
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn enclosing(path: &str, content: &str, index: usize, open: char) -> Option<[usize; 2]>
+    {
+        TBrackets::default().enclosing(content, TSyntax::detect(path, content).as_mut(), index, open)
+    }
+
+    #[test]
+    fn finds_enclosing_pairs()
+    {
+        let content = "foo(a, \")\", b(c)) + x\n";
+        assert_eq!(enclosing("a.rs", content, 4, '('), Some([3, 16]));
+        assert_eq!(enclosing("a.rs", content, 14, '('), Some([13, 15]));
+        assert_eq!(enclosing("a.rs", content, 16, '('), Some([3, 16]));
+        assert_eq!(enclosing("a.rs", content, 3, '('), Some([3, 16]));
+        assert_eq!(enclosing("a.rs", content, 20, '('), None);
+        assert_eq!(enclosing("a.rs", content, 4, '['), None);
+    }
+
+    #[test]
+    fn skips_brackets_in_comments_across_lines()
+    {
+        assert_eq!(enclosing("a.rs", "bar(1, /* ( */ 2)", 15, '('), Some([3, 16]));
+        assert_eq!(enclosing("a.rs", "fn a() {\n    b; // }\n}\n", 13, '{'), Some([7, 21]));
+    }
+}
@@ -0,0 +1,309 @@
+use std::env;
+use std::fs;
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+use crate::t_config::{entries, TValue};
+use crate::t_dirs::config_dir;
+
+
+pub const SCOPES: [&str; 10] = ["comment", "string", "number", "keyword", "type", "function", "constant", "operator", "property", "heading"];
+
+const BUILT_IN: [(&str, &str); 4] = [
+    ("default", include_str!("../themes/default.toml")),
+    ("dark", include_str!("../themes/dark.toml")),
+    ("light", include_str!("../themes/light.toml")),
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+];
+
+const ANSI: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (128, 0, 0), (0, 128, 0), (128, 128, 0), (0, 0, 128), (128, 0, 128), (0, 128, 128), (192, 192, 192),
+    (128, 128, 128), (255, 0, 0), (0, 255, 0), (255, 255, 0), (0, 0, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TColors
+{
+    Truecolor,
+    Ansi256,
+    Ansi16,
+}
+
+
+impl TColors
+{
+    pub fn parse(name: &str) -> Option<TColors>
+    {
+        match name
+        {
+            "truecolor" | "24bit" => Some(TColors::Truecolor),
+            "256" => Some(TColors::Ansi256),
+            "16" => Some(TColors::Ansi16),
+            _ => None,
+        }
+    }
+
+    pub fn detect() -> TColors
+    {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+
+        if colorterm == "truecolor" || colorterm == "24bit"
+        {
+            TColors::Truecolor
+        }
+        else if term.contains("256")
+        {
+            TColors::Ansi256
+        }
+        else
+        {
+            TColors::Ansi16
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct TTheme
+{
+    pub name: String,
+    pub text: ContentStyle,
+    pub gutter: ContentStyle,
+    pub status: ContentStyle,
+    pub tab: ContentStyle,
+    pub selection: ContentStyle,
+    pub search: ContentStyle,
+    pub matching: ContentStyle,
+    pub mismatch: ContentStyle,
+    pub ruler: ContentStyle,
+    pub syntax: [ContentStyle; SCOPES.len()],
+    pub directory: ContentStyle,
+    pub file: ContentStyle,
+    pub tree: ContentStyle,
+}
+
+
+impl TTheme
+{
+    pub fn load(name: &str, colors: TColors) -> Result<TTheme, String>
+    {
+        let user = config_dir().and_then(|dir| fs::read_to_string(dir.join("themes").join(format!("{name}.toml"))).ok());
+        let content = match user
+        {
+            Some(content) => content,
+            None => BUILT_IN.iter().find(|(n, _)| *n == name).ok_or(format!("Unknown theme {name}."))?.1.to_string(),
+        };
+
+        TTheme::parse(name, content.as_str(), colors)
+    }
+
+    // the fallback when the configured theme is broken, so a user default.toml is not tried again
+    pub fn built_in(colors: TColors) -> TTheme
+    {
+        TTheme::parse(BUILT_IN[0].0, BUILT_IN[0].1, colors).unwrap_or_else(|_| TTheme::plain(BUILT_IN[0].0))
+    }
+
+    fn plain(name: &str) -> TTheme
+    {
+        TTheme { name: name.to_string(),
+            text: ContentStyle::new(),
+            gutter: ContentStyle::new(),
+            status: ContentStyle::new(),
+            tab: ContentStyle::new(),
+            selection: ContentStyle::new(),
+            search: ContentStyle::new(),
+            matching: ContentStyle::new(),
+            mismatch: ContentStyle::new(),
+            ruler: ContentStyle::new(),
+            syntax: [ContentStyle::new(); SCOPES.len()],
+            directory: ContentStyle::new(),
+            file: ContentStyle::new(),
+            tree: ContentStyle::new(),
+        }
+    }
+
+    fn parse(name: &str, content: &str, colors: TColors) -> Result<TTheme, String>
+    {
+        let mut t_theme = TTheme::plain(name);
+        for (line, entry) in entries(content)
+        {
+            entry.and_then(|(key, value)| t_theme.set(key.as_str(), value, colors))
+                .map_err(|err| format!("Theme {name} line {line}: {err}"))?;
+        }
+
+        Ok(t_theme)
+    }
+
+    pub fn names() -> Vec<String>
+    {
+        let mut names: Vec<String> = BUILT_IN.iter().map(|(name, _)| name.to_string()).collect();
+
+        if let Some(Ok(dir)) = config_dir().map(|dir| fs::read_dir(dir.join("themes")))
+        {
+            for entry in dir.flatten()
+            {
+                let path = entry.path();
+                match (path.file_stem(), path.extension())
+                {
+                    (Some(stem), Some(extension)) if extension == "toml" => names.push(stem.to_string_lossy().into_owned()),
+                    _ => {},
+                }
+            }
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn set(&mut self, key: &str, value: TValue, colors: TColors) -> Result<(), &'static str>
+    {
+        let TValue::String(value) = value else {
+            return Err("Expected a style string.");
+        };
+        let style = parse_style(value.as_str(), colors)?;
+
+        let target = match key
+        {
+            "text" => &mut self.text,
+            "gutter" => &mut self.gutter,
+            "status" => &mut self.status,
+            "tab" => &mut self.tab,
+            "selection" => &mut self.selection,
+            "search" => &mut self.search,
+            "match" => &mut self.matching,
+            "mismatch" => &mut self.mismatch,
+            "ruler" => &mut self.ruler,
+            "explorer.directory" => &mut self.directory,
+            "explorer.file" => &mut self.file,
+            "explorer.tree" => &mut self.tree,
+            _ => match key.strip_prefix("syntax.").and_then(|scope| SCOPES.iter().position(|s| *s == scope))
+            {
+                Some(i) => &mut self.syntax[i],
+                None => return Err("Unknown theme key."),
+            },
+        };
+        *target = style;
+
+        Ok(())
+    }
+
+    pub fn style(&self, style: ContentStyle) -> ContentStyle
+    {
+        let mut attributes = self.text.attributes;
+        attributes.extend(style.attributes);
+
+        ContentStyle {
+            foreground_color: style.foreground_color.or(self.text.foreground_color),
+            background_color: style.background_color.or(self.text.background_color),
+            underline_color: style.underline_color.or(self.text.underline_color),
+            attributes,
+        }
+    }
+}
+
+
+fn parse_style(value: &str, colors: TColors) -> Result<ContentStyle, &'static str>
+{
+    let mut style = ContentStyle::new();
+    let mut words = value.split_whitespace();
+
+    while let Some(word) = words.next()
+    {
+        match word
+        {
+            "on" => { style.background_color = parse_color(words.next().ok_or("Missing color after on.")?, colors)?; },
+            "bold" => { style.attributes.set(Attribute::Bold); },
+            "dim" => { style.attributes.set(Attribute::Dim); },
+            "italic" => { style.attributes.set(Attribute::Italic); },
+            "underline" => { style.attributes.set(Attribute::Underlined); },
+            "reverse" => { style.attributes.set(Attribute::Reverse); },
+            _ => { style.foreground_color = parse_color(word, colors)?; },
+        }
+    }
+
+    Ok(style)
+}
+
+
+fn parse_color(word: &str, colors: TColors) -> Result<Option<Color>, &'static str>
+{
+    let color = if word == "default"
+    {
+        return Ok(None);
+    }
+    else if let Some(hex) = word.strip_prefix('#').filter(|h| h.len() == 6)
+    {
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "Invalid hex color.");
+        Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? }
+    }
+    else if let Ok(value) = word.parse::<u8>()
+    {
+        Color::AnsiValue(value)
+    }
+    else
+    {
+        Color::try_from(word.replace("gray", "grey").as_str()).map_err(|_| "Unknown color.")?
+    };
+
+    Ok(Some(downsample(color, colors)))
+}
+
+
+fn downsample(color: Color, colors: TColors) -> Color
+{
+    match (color, colors)
+    {
+        (Color::Rgb { r, g, b }, TColors::Ansi256) => Color::AnsiValue(ansi256(r, g, b)),
+        (Color::Rgb { .. } | Color::AnsiValue(_), TColors::Ansi16) => {
+            let (r, g, b) = rgb(color);
+            let nearest = ANSI.iter()
+                .enumerate()
+                .min_by_key(|(_, (ar, ag, ab))| {
+                    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+                    d(r, *ar) + d(g, *ag) + d(b, *ab)
+                })
+                .unwrap().0;
+            Color::AnsiValue(nearest as u8)
+        },
+        _ => color,
+    }
+}
+
+
+fn ansi256(r: u8, g: u8, b: u8) -> u8
+{
+    if r == g && g == b
+    {
+        return match r
+        {
+            0..8 => 16,
+            249.. => 231,
+            _ => 232 + ((r as u16 - 8) * 24 / 247) as u8,
+        };
+    }
+
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+
+fn rgb(color: Color) -> (u8, u8, u8)
+{
+    match color
+    {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(n) if n < 16 => ANSI[n as usize],
+        Color::AnsiValue(n) if n >= 232 => {
+            let level = 8 + (n - 232) * 10;
+            (level, level, level)
+        },
+        Color::AnsiValue(n) => {
+            let level = |c: u8| if c == 0 { 0 } else { 55 + c * 40 };
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        },
+        _ => (0, 0, 0),
+    }
+}
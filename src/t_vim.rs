@@ -0,0 +1,702 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum TMotion
+{
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    FindForward(char),
+    TillForward(char),
+    FindBackward(char),
+    TillBackward(char),
+    MatchBracket,
+}
+
+
+impl TMotion
+{
+    pub fn inclusive(&self) -> bool
+    {
+        matches!(
+            self,
+            TMotion::WordEnd | TMotion::LineEnd | TMotion::FindForward(_) | TMotion::TillForward(_) | TMotion::MatchBracket
+        )
+    }
+
+    pub fn linewise(&self) -> bool
+    {
+        matches!(self, TMotion::Up | TMotion::Down | TMotion::FirstLine | TMotion::LastLine)
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TObject
+{
+    Word(bool),
+    Pair(char, bool),
+    Quote(char, bool),
+    Paragraph(bool),
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TOperator
+{
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TTarget
+{
+    Motion(TMotion),
+    Object(TObject),
+    Line,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TAction
+{
+    Move(TMotion),
+    Operate(TOperator, TTarget),
+    Key(char),
+    Repeat,
+    Record(char),
+    Replay(char),
+    Mark(char),
+    JumpMark(char, bool),
+}
+
+
+pub struct TVimCommand
+{
+    pub count: Option<usize>,
+    pub action: TAction,
+}
+
+
+pub enum TParse<T>
+{
+    Incomplete,
+    Invalid,
+    Complete(T),
+}
+
+
+#[derive(Default, Clone)]
+pub struct TRegister
+{
+    pub text: String,
+    pub linewise: bool,
+}
+
+
+pub fn parse(keys: &[char]) -> TParse<TVimCommand>
+{
+    let (count, keys) = parse_count(keys);
+
+    let key = match keys.first()
+    {
+        None => return TParse::Incomplete,
+        Some(k) => *k,
+    };
+
+    let operator = match key
+    {
+        'd' => TOperator::Delete,
+        'c' => TOperator::Change,
+        'y' => TOperator::Yank,
+        '>' => TOperator::Indent,
+        '<' => TOperator::Dedent,
+        '.' => return TParse::Complete(TVimCommand { count, action: TAction::Repeat }),
+        'q' | '@' | 'm' | '\'' | '`' => return match keys.get(1)
+        {
+            None => TParse::Incomplete,
+            Some(name) => {
+                let action = match key
+                {
+                    'q' => TAction::Record(*name),
+                    '@' => TAction::Replay(*name),
+                    'm' => TAction::Mark(*name),
+                    _ => TAction::JumpMark(*name, key == '`'),
+                };
+                TParse::Complete(TVimCommand { count, action })
+            },
+        },
+        _ => return match parse_motion(keys)
+        {
+            TParse::Incomplete => TParse::Incomplete,
+            TParse::Complete(motion) => TParse::Complete(TVimCommand { count, action: TAction::Move(motion) }),
+            TParse::Invalid if keys.len() == 1 => TParse::Complete(TVimCommand { count, action: TAction::Key(key) }),
+            TParse::Invalid => TParse::Invalid,
+        },
+    };
+
+    let (motion_count, rest) = parse_count(&keys[1..]);
+    let count = match (count, motion_count)
+    {
+        (Some(a), Some(b)) => Some(a * b),
+        (a, b) => a.or(b),
+    };
+
+    let target = match rest.first()
+    {
+        None => return TParse::Incomplete,
+        Some(k) if *k == key => TTarget::Line,
+        Some('i') | Some('a') => match rest.get(1)
+        {
+            None => return TParse::Incomplete,
+            Some(c) => match parse_object(*c, rest[0] == 'a')
+            {
+                None => return TParse::Invalid,
+                Some(object) => TTarget::Object(object),
+            },
+        },
+        Some(_) => match parse_motion(rest)
+        {
+            TParse::Incomplete => return TParse::Incomplete,
+            TParse::Invalid => return TParse::Invalid,
+            TParse::Complete(motion) => TTarget::Motion(motion),
+        },
+    };
+
+    TParse::Complete(TVimCommand { count, action: TAction::Operate(operator, target) })
+}
+
+
+fn parse_count(keys: &[char]) -> (Option<usize>, &[char])
+{
+    let digits = keys.iter()
+        .enumerate()
+        .take_while(|(i, c)| c.is_ascii_digit() && !(*i == 0 && **c == '0'))
+        .count();
+
+    if digits == 0
+    {
+        return (None, keys);
+    }
+
+    let count = keys[..digits].iter().collect::<String>().parse().ok();
+
+    (count, &keys[digits..])
+}
+
+
+pub fn parse_motion(keys: &[char]) -> TParse<TMotion>
+{
+    let motion = match keys.first()
+    {
+        None => return TParse::Incomplete,
+        Some('h') => TMotion::Left,
+        Some('l') => TMotion::Right,
+        Some('k') => TMotion::Up,
+        Some('j') => TMotion::Down,
+        Some('w') => TMotion::WordForward,
+        Some('b') => TMotion::WordBackward,
+        Some('e') => TMotion::WordEnd,
+        Some('0') => TMotion::LineStart,
+        Some('^') => TMotion::FirstNonBlank,
+        Some('$') => TMotion::LineEnd,
+        Some('G') => TMotion::LastLine,
+        Some('%') => TMotion::MatchBracket,
+        Some('g') => match keys.get(1)
+        {
+            None => return TParse::Incomplete,
+            Some('g') => TMotion::FirstLine,
+            Some(_) => return TParse::Invalid,
+        },
+        Some(c @ ('f' | 't' | 'F' | 'T')) => match keys.get(1)
+        {
+            None => return TParse::Incomplete,
+            Some(target) => match c
+            {
+                'f' => TMotion::FindForward(*target),
+                't' => TMotion::TillForward(*target),
+                'F' => TMotion::FindBackward(*target),
+                _ => TMotion::TillBackward(*target),
+            },
+        },
+        Some(_) => return TParse::Invalid,
+    };
+
+    TParse::Complete(motion)
+}
+
+
+fn parse_object(key: char, around: bool) -> Option<TObject>
+{
+    match key
+    {
+        'w' => Some(TObject::Word(around)),
+        '(' | ')' | 'b' => Some(TObject::Pair('(', around)),
+        '[' | ']' => Some(TObject::Pair('[', around)),
+        '{' | '}' | 'B' => Some(TObject::Pair('{', around)),
+        '"' | '\'' | '`' => Some(TObject::Quote(key, around)),
+        'p' => Some(TObject::Paragraph(around)),
+        _ => None,
+    }
+}
+
+
+pub fn line_start(content: &str, index: usize) -> usize
+{
+    content[..index].rfind('\n').map_or(0, |i| i + 1)
+}
+
+
+pub fn line_end(content: &str, index: usize) -> usize
+{
+    content[index..].find('\n').map_or(content.len(), |i| index + i)
+}
+
+
+pub fn first_non_blank(content: &str, index: usize) -> usize
+{
+    let start = line_start(content, index);
+    let line = &content[start..line_end(content, index)];
+
+    start + line.len() - line.trim_start().len()
+}
+
+
+fn next_line(content: &str, index: usize) -> Option<usize>
+{
+    content[index..].find('\n').map(|i| index + i + 1)
+}
+
+
+fn previous_line(content: &str, index: usize) -> Option<usize>
+{
+    let start = line_start(content, index);
+
+    (start > 0).then(|| line_start(content, start - 1))
+}
+
+
+pub fn line_span(content: &str, from: usize, to: usize) -> [usize; 2]
+{
+    let start = line_start(content, from);
+    let end = line_end(content, to);
+
+    if end < content.len()
+    {
+        [start, end + 1]
+    }
+    else
+    {
+        [start.saturating_sub(1), end]
+    }
+}
+
+
+fn class(c: char) -> u8
+{
+    if c.is_whitespace()
+    {
+        0
+    }
+    else if c.is_alphanumeric() || c == '_'
+    {
+        1
+    }
+    else
+    {
+        2
+    }
+}
+
+
+fn char_len(content: &str, index: usize) -> usize
+{
+    content[index..].chars().next().map_or(0, |c| c.len_utf8())
+}
+
+
+fn word_forward(content: &str, index: usize) -> usize
+{
+    let mut chars = content[index..].char_indices().peekable();
+    let (mut newline, first) = match chars.next()
+    {
+        None => return index,
+        Some((_, c)) => (c == '\n', class(c)),
+    };
+
+    if first != 0
+    {
+        while chars.next_if(|(_, c)| class(*c) == first).is_some() {}
+    }
+
+    while let Some((i, c)) = chars.next_if(|(_, c)| class(*c) == 0)
+    {
+        if c == '\n' && newline
+        {
+            return index + i;
+        }
+        newline = c == '\n';
+    }
+
+    chars.peek().map_or(content.len(), |(i, _)| index + i)
+}
+
+
+fn word_backward(content: &str, index: usize) -> usize
+{
+    let mut chars = content[..index].char_indices().rev().peekable();
+
+    while chars.next_if(|(_, c)| class(*c) == 0).is_some() {}
+
+    let kind = match chars.peek()
+    {
+        None => return 0,
+        Some((_, c)) => class(*c),
+    };
+
+    let mut position = 0;
+    while let Some((i, _)) = chars.next_if(|(_, c)| class(*c) == kind)
+    {
+        position = i;
+    }
+
+    position
+}
+
+
+fn word_end(content: &str, index: usize) -> usize
+{
+    let mut chars = content[index..].char_indices().skip(1).peekable();
+
+    while chars.next_if(|(_, c)| class(*c) == 0).is_some() {}
+
+    let (mut position, kind) = match chars.next()
+    {
+        None => return index,
+        Some((i, c)) => (i, class(c)),
+    };
+
+    while let Some((i, _)) = chars.next_if(|(_, c)| class(*c) == kind)
+    {
+        position = i;
+    }
+
+    index + position
+}
+
+
+fn find_in_line(content: &str, index: usize, target: char, forward: bool, count: usize) -> Option<usize>
+{
+    if forward
+    {
+        let start = index + char_len(content, index);
+        let end = line_end(content, index).max(start);
+        content[start..end].match_indices(target).nth(count - 1).map(|(i, _)| start + i)
+    }
+    else
+    {
+        let start = line_start(content, index);
+        content[start..index].rmatch_indices(target).nth(count - 1).map(|(i, _)| start + i)
+    }
+}
+
+
+pub fn motion_target(content: &str, index: usize, motion: TMotion, count: Option<usize>) -> Option<usize>
+{
+    let n = count.unwrap_or(1);
+
+    let target = match motion
+    {
+        TMotion::Left => {
+            let start = line_start(content, index);
+            content[start..index].char_indices().rev().nth(n - 1).map_or(start, |(i, _)| start + i)
+        },
+        TMotion::Right => {
+            let end = line_end(content, index);
+            content[index..end].char_indices().nth(n).map_or(end, |(i, _)| index + i)
+        },
+        TMotion::Up | TMotion::Down => {
+            let column = content[line_start(content, index)..index].chars().count();
+            let mut line = index;
+            for _ in 0..n
+            {
+                let step = if motion == TMotion::Up { previous_line(content, line) } else { next_line(content, line) };
+                match step
+                {
+                    None => break,
+                    Some(l) => line = l,
+                }
+            }
+            let line = line_start(content, line);
+            let text = &content[line..line_end(content, line)];
+            line + text.char_indices().nth(column).map_or(text.len(), |(i, _)| i)
+        },
+        TMotion::WordForward => (0..n).fold(index, |i, _| word_forward(content, i)),
+        TMotion::WordBackward => (0..n).fold(index, |i, _| word_backward(content, i)),
+        TMotion::WordEnd => (0..n).fold(index, |i, _| word_end(content, i)),
+        TMotion::LineStart => line_start(content, index),
+        TMotion::FirstNonBlank => first_non_blank(content, index),
+        TMotion::LineEnd => {
+            let mut line = index;
+            for _ in 1..n
+            {
+                line = next_line(content, line).unwrap_or(line);
+            }
+            let start = line_start(content, line);
+            let end = line_end(content, line);
+            content[start..end].char_indices().next_back().map_or(end, |(i, _)| start + i)
+        },
+        TMotion::FirstLine | TMotion::LastLine => {
+            let line = match (motion, count)
+            {
+                (_, Some(line)) => line,
+                (TMotion::FirstLine, None) => 1,
+                _ => usize::MAX,
+            };
+            let start = match line
+            {
+                0 | 1 => 0,
+                _ => content.match_indices('\n').nth(line - 2).map_or(line_start(content, content.len()), |(i, _)| i + 1),
+            };
+            first_non_blank(content, start)
+        },
+        TMotion::FindForward(c) => find_in_line(content, index, c, true, n)?,
+        TMotion::TillForward(c) => {
+            let found = find_in_line(content, index, c, true, n)?;
+            content[..found].char_indices().next_back().map_or(found, |(i, _)| i)
+        },
+        TMotion::FindBackward(c) => find_in_line(content, index, c, false, n)?,
+        TMotion::TillBackward(c) => {
+            let found = find_in_line(content, index, c, false, n)?;
+            found + char_len(content, found)
+        },
+        // brackets are matched by TFile, which skips the ones in strings and comments
+        TMotion::MatchBracket => return None,
+    };
+
+    Some(target)
+}
+
+
+pub fn motion_range(content: &str, index: usize, motion: TMotion, count: Option<usize>, change: bool) -> Option<([usize; 2], bool)>
+{
+    let motion = match motion
+    {
+        TMotion::WordForward if change && content[index..].starts_with(|c: char| !c.is_whitespace()) => TMotion::WordEnd,
+        m => m,
+    };
+
+    let target = motion_target(content, index, motion, count)?;
+
+    if motion.linewise()
+    {
+        return Some((line_span(content, index.min(target), index.max(target)), true));
+    }
+
+    let start = index.min(target);
+    let mut end = index.max(target);
+
+    if motion.inclusive() && !content[end..].starts_with('\n')
+    {
+        end += char_len(content, end);
+    }
+
+    if motion == TMotion::WordForward && !content[start..].starts_with('\n')
+    {
+        end = end.min(line_end(content, start));
+    }
+
+    Some(([start, end], false))
+}
+
+
+pub fn object_range(content: &str, index: usize, object: TObject) -> Option<([usize; 2], bool)>
+{
+    match object
+    {
+        TObject::Word(around) => {
+            let start = line_start(content, index);
+            let end = line_end(content, index);
+            let kind = content[index..end].chars().next().map(class)?;
+
+            let first = content[start..index].char_indices().rev()
+                .take_while(|(_, c)| class(*c) == kind)
+                .last()
+                .map_or(index, |(i, _)| start + i);
+            let mut last = content[index..end].char_indices()
+                .find(|(_, c)| class(*c) != kind)
+                .map_or(end, |(i, _)| index + i);
+
+            let mut first = first;
+            if around
+            {
+                let trailing = content[last..end].len() - content[last..end].trim_start().len();
+                if trailing > 0
+                {
+                    last += trailing;
+                }
+                else
+                {
+                    first -= content[start..first].len() - content[start..first].trim_end().len();
+                }
+            }
+
+            Some(([first, last], false))
+        },
+        TObject::Pair(_, _) => None,
+        TObject::Quote(quote, around) => {
+            let start = line_start(content, index);
+            let end = line_end(content, index);
+            let quotes: Vec<usize> = content[start..end].match_indices(quote).map(|(i, _)| start + i).collect();
+
+            let pair = quotes.chunks(2)
+                .filter(|pair| pair.len() == 2)
+                .find(|pair| index >= pair[0] && index <= pair[1])
+                .or_else(|| quotes.chunks(2).filter(|pair| pair.len() == 2).find(|pair| pair[0] > index))?;
+
+            if around
+            {
+                Some(([pair[0], pair[1] + quote.len_utf8()], false))
+            }
+            else
+            {
+                Some(([pair[0] + quote.len_utf8(), pair[1]], false))
+            }
+        },
+        TObject::Paragraph(around) => {
+            let blank = |line: usize| content[line..line_end(content, line)].trim().is_empty();
+            let kind = blank(line_start(content, index));
+
+            let mut first = line_start(content, index);
+            while let Some(previous) = previous_line(content, first).filter(|l| blank(*l) == kind)
+            {
+                first = previous;
+            }
+
+            let mut last = line_start(content, index);
+            while let Some(next) = next_line(content, last).filter(|l| *l < content.len() && blank(*l) == kind)
+            {
+                last = next;
+            }
+
+            if around
+            {
+                while let Some(next) = next_line(content, last).filter(|l| *l < content.len() && blank(*l) != kind)
+                {
+                    last = next;
+                }
+            }
+
+            Some((line_span(content, first, last), true))
+        },
+    }
+}
+
+
+pub fn shift_lines(text: &str, indent: &str, right: bool) -> String
+{
+    text.split('\n').map(|line| {
+        if line.trim().is_empty()
+        {
+            line.to_string()
+        }
+        else if right
+        {
+            format!("{indent}{line}")
+        }
+        else if let Some(rest) = line.strip_prefix('\t')
+        {
+            rest.to_string()
+        }
+        else
+        {
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            line[spaces.min(indent.len())..].to_string()
+        }
+    }).collect::<Vec<String>>().join("\n")
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn command(keys: &str) -> Option<(Option<usize>, TAction)>
+    {
+        match parse(&keys.chars().collect::<Vec<char>>())
+        {
+            TParse::Complete(command) => Some((command.count, command.action)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_counts()
+    {
+        assert!(command("3dw") == Some((Some(3), TAction::Operate(TOperator::Delete, TTarget::Motion(TMotion::WordForward)))));
+        assert!(command("2d3w") == Some((Some(6), TAction::Operate(TOperator::Delete, TTarget::Motion(TMotion::WordForward)))));
+        assert!(command("10j") == Some((Some(10), TAction::Move(TMotion::Down))));
+        assert!(command("0") == Some((None, TAction::Move(TMotion::LineStart))));
+        assert!(command("2dd") == Some((Some(2), TAction::Operate(TOperator::Delete, TTarget::Line))));
+        assert!(command("d").is_none() && command("dz").is_none() && command("g").is_none());
+    }
+
+    #[test]
+    fn parses_objects_and_repeats()
+    {
+        assert!(command("ci(") == Some((None, TAction::Operate(TOperator::Change, TTarget::Object(TObject::Pair('(', false))))));
+        assert!(command("dab") == Some((None, TAction::Operate(TOperator::Delete, TTarget::Object(TObject::Pair('(', true))))));
+        assert!(command("ya\"") == Some((None, TAction::Operate(TOperator::Yank, TTarget::Object(TObject::Quote('"', true))))));
+        assert!(command("di<").is_none());
+        assert!(command(".") == Some((None, TAction::Repeat)));
+        assert!(command("3.") == Some((Some(3), TAction::Repeat)));
+    }
+
+    #[test]
+    fn deletes_and_changes_words()
+    {
+        assert_eq!(motion_range("foo bar", 0, TMotion::WordForward, None, false), Some(([0, 4], false)));
+        assert_eq!(motion_range("a b c", 0, TMotion::WordForward, Some(2), false), Some(([0, 4], false)));
+        assert_eq!(motion_range("foo\nbar", 0, TMotion::WordForward, None, false), Some(([0, 3], false)));
+        assert_eq!(motion_range("foo bar", 0, TMotion::WordForward, None, true), Some(([0, 3], false)));
+        assert_eq!(motion_range("foo  bar", 3, TMotion::WordForward, None, true), Some(([3, 5], false)));
+        assert_eq!(motion_range("foo.bar", 0, TMotion::WordForward, None, false), Some(([0, 3], false)));
+    }
+
+    #[test]
+    fn moves_by_motions()
+    {
+        let content = "one two\n  three\n";
+        assert_eq!(motion_target(content, 0, TMotion::WordForward, Some(2)), Some(10));
+        assert_eq!(motion_target(content, 10, TMotion::WordBackward, None), Some(4));
+        assert_eq!(motion_target(content, 0, TMotion::LineEnd, None), Some(6));
+        assert_eq!(motion_target(content, 4, TMotion::Down, None), Some(12));
+        assert_eq!(motion_target(content.trim_end(), 0, TMotion::LastLine, None), Some(10));
+        assert_eq!(motion_target(content, 0, TMotion::TillForward('t'), None), Some(3));
+        assert_eq!(motion_target(content, 0, TMotion::FindForward('x'), None), None);
+    }
+
+    #[test]
+    fn selects_quotes_and_words()
+    {
+        let content = "say \"hi there\" now";
+        assert_eq!(object_range(content, 6, TObject::Quote('"', false)), Some(([5, 13], false)));
+        assert_eq!(object_range(content, 6, TObject::Quote('"', true)), Some(([4, 14], false)));
+        assert_eq!(object_range(content, 0, TObject::Quote('"', false)), Some(([5, 13], false)));
+        assert_eq!(object_range(content, 16, TObject::Quote('"', false)), None);
+        assert_eq!(object_range(content, 1, TObject::Word(false)), Some(([0, 3], false)));
+        assert_eq!(object_range(content, 1, TObject::Word(true)), Some(([0, 4], false)));
+    }
+}
@@ -0,0 +1,213 @@
+#[derive(Clone, Copy, PartialEq)]
+pub struct TRect
+{
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+
+impl TRect
+{
+    pub fn contains(&self, x: u16, y: u16) -> bool
+    {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    fn split(&self, split: TSplit, ratio: u16) -> (TRect, TRect, TRect)
+    {
+        match split
+        {
+            TSplit::Horizontal => {
+                let available = self.height.saturating_sub(1);
+                let first = ((available as u32 * ratio as u32 / 100) as u16).clamp(1.min(available), available);
+                (
+                    TRect { height: first, ..*self },
+                    TRect { y: self.y + first, height: 1.min(self.height - first), ..*self },
+                    TRect { y: self.y + first + 1, height: available - first, ..*self },
+                )
+            },
+            TSplit::Vertical => {
+                let available = self.width.saturating_sub(1);
+                let first = ((available as u32 * ratio as u32 / 100) as u16).clamp(1.min(available), available);
+                (
+                    TRect { width: first, ..*self },
+                    TRect { x: self.x + first, width: 1.min(self.width - first), ..*self },
+                    TRect { x: self.x + first + 1, width: available - first, ..*self },
+                )
+            },
+        }
+    }
+}
+
+
+pub struct TWindow
+{
+    pub buffer: usize,
+    pub index: usize,
+    pub view: [usize; 2],
+    pub offset: usize,
+    pub area: TRect,
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TSplit
+{
+    Horizontal,
+    Vertical,
+}
+
+
+pub enum TLayout
+{
+    Window(usize),
+    Split(TSplit, u16, Box<TLayout>, Box<TLayout>),
+}
+
+
+impl TLayout
+{
+    pub fn arrange(&self, area: TRect, windows: &mut [TWindow], separators: &mut Vec<(TSplit, TRect)>)
+    {
+        match self
+        {
+            TLayout::Window(i) => { windows[*i].area = area; },
+            TLayout::Split(split, ratio, first, second) => {
+                let (a, separator, b) = area.split(*split, *ratio);
+                first.arrange(a, windows, separators);
+                separators.push((*split, separator));
+                second.arrange(b, windows, separators);
+            },
+        }
+    }
+
+    pub fn split(&mut self, window: usize, split: TSplit, new: usize) -> bool
+    {
+        match self
+        {
+            TLayout::Window(i) if *i == window => {
+                *self = TLayout::Split(split, 50, Box::new(TLayout::Window(window)), Box::new(TLayout::Window(new)));
+                true
+            },
+            TLayout::Window(_) => false,
+            TLayout::Split(_, _, first, second) => first.split(window, split, new) || second.split(window, split, new),
+        }
+    }
+
+    pub fn close(&mut self, window: usize) -> bool
+    {
+        let TLayout::Split(_, _, first, second) = self else { return false };
+
+        let sibling = if matches!(**first, TLayout::Window(i) if i == window)
+        {
+            std::mem::replace(&mut **second, TLayout::Window(0))
+        }
+        else if matches!(**second, TLayout::Window(i) if i == window)
+        {
+            std::mem::replace(&mut **first, TLayout::Window(0))
+        }
+        else
+        {
+            return first.close(window) || second.close(window);
+        };
+
+        *self = sibling;
+
+        true
+    }
+
+    pub fn renumber(&mut self, removed: usize)
+    {
+        match self
+        {
+            TLayout::Window(i) => {
+                if *i > removed
+                {
+                    *i -= 1;
+                }
+            },
+            TLayout::Split(_, _, first, second) => {
+                first.renumber(removed);
+                second.renumber(removed);
+            },
+        }
+    }
+
+    pub fn resize(&mut self, window: usize, split: TSplit, delta: i16) -> Option<bool>
+    {
+        match self
+        {
+            TLayout::Window(i) => if *i == window { Some(false) } else { None },
+            TLayout::Split(s, ratio, first, second) => {
+                let (resized, in_first) = match first.resize(window, split, delta)
+                {
+                    Some(r) => (r, true),
+                    None => (second.resize(window, split, delta)?, false),
+                };
+
+                if resized || *s != split
+                {
+                    return Some(resized);
+                }
+
+                let delta = if in_first { delta } else { -delta };
+                *ratio = (*ratio as i16 + delta).clamp(10, 90) as u16;
+
+                Some(true)
+            },
+        }
+    }
+
+    pub fn equalize(&mut self)
+    {
+        if let TLayout::Split(_, ratio, first, second) = self
+        {
+            *ratio = 50;
+            first.equalize();
+            second.equalize();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn rect(height: u16) -> TRect
+    {
+        TRect { x: 0, y: 0, width: 80, height }
+    }
+
+    #[test]
+    fn splits_large_areas_without_overflow()
+    {
+        let (first, separator, second) = rect(1000).split(TSplit::Horizontal, 90);
+        assert_eq!((first.height, separator.y, separator.height), (899, 899, 1));
+        assert_eq!((second.y, second.height), (900, 100));
+    }
+
+    #[test]
+    fn clamps_ratios()
+    {
+        let (first, _, second) = rect(11).split(TSplit::Horizontal, 0);
+        assert_eq!((first.height, second.height), (1, 9));
+        let (first, _, second) = rect(11).split(TSplit::Horizontal, 100);
+        assert_eq!((first.height, second.height), (10, 0));
+    }
+
+    #[test]
+    fn splits_tiny_areas()
+    {
+        for height in 0..3
+        {
+            let (first, separator, second) = rect(height).split(TSplit::Horizontal, 50);
+            assert_eq!(first.height + separator.height + second.height, height);
+        }
+        let (first, separator, second) = TRect { x: 0, y: 0, width: 1, height: 5 }.split(TSplit::Vertical, 50);
+        assert_eq!((first.width, separator.width, second.width), (0, 1, 0));
+    }
+}